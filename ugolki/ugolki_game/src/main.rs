@@ -1,38 +1,70 @@
 use raylib::prelude::*;
 use ugolki_solver::*;
+use ugolki_solver::game::Game;
+use ugolki_solver::pgn::{self, PgnGame};
 
 struct TurnSearchPayload {
     col: PieceColor,
     board: BoardPosition,
     steps: usize,
-    depth: usize,
+    budget: std::time::Duration,
 }
 
 const CHECKER_IMG_BYTES: &[u8] = include_bytes!("../assets/checker_white.png");
-const MAX_SEARCH_DEPTH: usize = 4;
+const SEARCH_TIME_BUDGET: std::time::Duration = std::time::Duration::from_millis(1500);
 const MAX_EVAL_DEPTH: usize = 4;
 const ANIMATION_STEP: f32 = 0.1;
+/// Undo / redo keys, in game against the engine they take back whole player's turn
+const UNDO_KEY: KeyboardKey = KeyboardKey::KEY_Z;
+const REDO_KEY: KeyboardKey = KeyboardKey::KEY_Y;
+/// Saves the game in portable game notation to `GAME_FILE`
+const SAVE_KEY: KeyboardKey = KeyboardKey::KEY_S;
+const GAME_FILE: &str = "ugolki_game.pgn";
+/// Color controlled by mouse, `None` to watch the engine play against itself
+const PLAYER_COLOR: Option<PieceColor> = Some(PieceColor::White);
+/// Board size and camps, one of `BoardConfig::VARIANTS` (unless position is given)
+const BOARD_CONFIG: &BoardConfig = &BoardConfig::CLASSIC;
+/// Allowed moves, one of `RuleSet::VARIANTS` (unless position is given)
+const RULES: RuleSet = RuleSet::CLASSIC;
 
 fn main() {
     raylib::set_trace_log(TraceLogLevel::LOG_WARNING);
+
+    // starting position (see `ugolki_solver::fen`) or `.pgn` game file can be given as the first argument
+    let mut game = match std::env::args().nth(1) {
+        Some(path) if path.ends_with(".pgn") => {
+            let text = std::fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("can't read `{path}`: {err}"));
+            text.parse::<PgnGame>()
+                .and_then(|record| record.replay())
+                .unwrap_or_else(|err| panic!("invalid game `{path}`: {err}"))
+        }
+        Some(fen) => Game::from_fen(
+            fen.parse()
+                .unwrap_or_else(|err| panic!("invalid position `{fen}`: {err}")),
+        ),
+        None => Game::new(BOARD_CONFIG, RULES),
+    };
+    let rules = game.rules();
+    let config = game.board().config();
+
+    let (width, height) = (config.width as i32, config.height as i32);
     
     let (mut rl, thread) = raylib::init()
-        .size(64 * 8, 64 * 8 + 32)
+        .size(64 * width, 64 * height + 32)
         .title("Ugolki")
         .build();
 
-    let mut board = BoardPosition::default();
-
-    let mut cur_pl = PieceColor::White;
-    let player_color: Option<PieceColor> = Some(PieceColor::White);
+    let player_color = PLAYER_COLOR;
     let eval_color = player_color.unwrap_or(PieceColor::White);
 
     let mut searcher = TurnSearcher::new();
+    searcher.set_rules(rules);
+    searcher.set_threads(std::thread::available_parallelism().map_or(1, usize::from));
 
     let mut delta = 0.;
     let mut eval_str = String::new();
     let mut turn_str = String::new();
-    let mut steps = 0;
     let mut finished = false;
     let mut waiting_for_turn = false;
 
@@ -46,20 +78,20 @@ fn main() {
 
     let mut moved: Option<(PieceColor, Turn, BoardPosition, usize)> = None;
     let mut picked: Option<(PieceColor, Position)> = None;
+    // continuation expected by the engine after its last turn
+    let mut expected_line: Vec<Turn> = Vec::new();
 
-    let (turn_sender, turn_reciver) = std::sync::mpsc::channel::<Turn>();
+    let (turn_sender, turn_reciver) = std::sync::mpsc::channel::<SearchResult>();
     let (payload_sender, payload_reciver) = std::sync::mpsc::channel::<TurnSearchPayload>();
 
     let _search_thread = std::thread::Builder::new()
         .name("Turn Search".to_owned())
-        .spawn(move || loop {
-            let Ok(TurnSearchPayload { col, board, steps, depth}) = payload_reciver.recv() else {
-                    break;
-                };
+        .spawn(move || {
+            while let Ok(TurnSearchPayload { col, board, steps, budget }) = payload_reciver.recv() {
+                let result = searcher.next_turn_timed(board, col, budget, steps);
 
-            let (_ev, turn) = searcher.next_turn(board, col, depth, steps);
-
-            turn_sender.send(turn).unwrap();
+                turn_sender.send(result).unwrap();
+            }
         })
         .unwrap();
 
@@ -67,81 +99,143 @@ fn main() {
 
     while !rl.window_should_close() {
         delta += rl.get_frame_time();
-        if !board.has_winner(steps) && moved.is_none() {
-            if player_color.map(|col| col == cur_pl).unwrap_or_default() {
-                if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+        let is_player_turn = |game: &Game| {
+            player_color.map(|col| col == game.side_to_move()).unwrap_or_default()
+        };
+        if !waiting_for_turn && moved.is_none() {
+            let undo = rl.is_key_pressed(UNDO_KEY);
+            let redo = rl.is_key_pressed(REDO_KEY);
+            if undo || redo {
+                // stop at the player's turn (or after a single turn when engine plays itself)
+                loop {
+                    let changed = if undo { game.undo() } else { game.redo() };
+                    if changed.is_none() || player_color.is_none() || is_player_turn(&game) {
+                        break;
+                    }
+                }
+                picked = None;
+                expected_line.clear();
+                finished = false;
+                eval_str.clear();
+                turn_str = format!("turn {}", game.turns_count());
+            }
+            if rl.is_key_pressed(SAVE_KEY) {
+                let (white, black) = match player_color {
+                    Some(PieceColor::White) => ("Player", "Engine"),
+                    Some(PieceColor::Black) => ("Engine", "Player"),
+                    None => ("Engine", "Engine"),
+                };
+                let mut record = PgnGame::from_game(&game);
+                record.set_tag("Event", "Ugolki game");
+                record.set_tag("Date", pgn::date(std::time::SystemTime::now()));
+                record.set_tag("White", white);
+                record.set_tag("Black", black);
+                record.set_tag("TimeControl", format!("{}ms", SEARCH_TIME_BUDGET.as_millis()));
+                eval_str = match std::fs::write(GAME_FILE, record.to_string()) {
+                    Ok(()) => format!("saved to {GAME_FILE}"),
+                    Err(err) => format!("can't save: {err}"),
+                };
+            }
+        }
+        if !game.is_finished() && !finished && moved.is_none() {
+            let cur_pl = game.side_to_move();
+            if is_player_turn(&game) {
+                if game.board().generate_all_turns_for_with(cur_pl, rules).next().is_none() {
+                    // blocked player can't continue either
+                    eval_str = format!("{cur_pl} has no turns");
+                    turn_str.clear();
+                    finished = true;
+                } else if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
                     // TODO if `displaypos` implements mirroring / rotating board also apply them here
                     let mouse = rl.get_mouse_position() / 64.;
 
-                    let pos_x = (mouse.x.floor() as i32).clamp(0, 7);
-                    let pos_y = (mouse.y.floor() as i32).clamp(0, 7);
-                    let pos_x = if flip_x { 7-pos_x } else { pos_x }; 
-                    let pos_y = if flip_y { 7-pos_y } else { pos_y }; 
+                    let pos_x = (mouse.x.floor() as i32).clamp(0, width - 1);
+                    let pos_y = (mouse.y.floor() as i32).clamp(0, height - 1);
+                    let pos_x = if flip_x { width - 1 - pos_x } else { pos_x }; 
+                    let pos_y = if flip_y { height - 1 - pos_y } else { pos_y }; 
             
                     let pos = Position::from_xy(pos_x as u8, pos_y as u8);
 
                     if let Some((col, picked_pos)) = picked {
                         debug_assert!(col == cur_pl);
-                        if board
-                            .generate_all_turns_for(cur_pl)
-                            .any(|turn| turn.from == picked_pos && turn.to == pos)
-                        {
-                            let turn = Turn {
-                                from: picked_pos,
-                                to: pos,
-                            };
-
-                            steps += 1;
+                        let turn = Turn {
+                            from: picked_pos,
+                            to: pos,
+                        };
+                        let board = game.board();
+                        if game.play(turn).is_ok() {
+                            if expected_line.first() == Some(&turn) {
+                                expected_line.remove(0);
+                            } else {
+                                expected_line.clear();
+                            }
+
                             eval_str = format!(
                                 "Eval({eval_color}): {}",
-                                board.deep_evaluate_for(eval_color, MAX_EVAL_DEPTH, steps)
+                                board.deep_evaluate_with(
+                                    eval_color,
+                                    rules,
+                                    MAX_EVAL_DEPTH,
+                                    game.turns_count()
+                                )
                             );
-                            turn_str = format!("turn {steps}");
+                            turn_str = format!("turn {}", game.turns_count());
 
                             moved = Some((cur_pl, turn, board, 0));
-
-                            board = board.perform_turn(turn, cur_pl);
-                            cur_pl = cur_pl.opposite();
                             delta = 0.;
                         }
                         picked = None;
-                    } else if board.0[cur_pl as usize].has_piece_at(pos) {
+                    } else if game.board().0[cur_pl as usize].has_piece_at(pos) {
                         picked = Some((cur_pl, pos));
                     }
                 }
             } else {
-                let depth = MAX_SEARCH_DEPTH; // if cur_pl == PieceColor::White { 6 } else { 6 };
+                let budget = SEARCH_TIME_BUDGET;
                 if !waiting_for_turn {
                     payload_sender
                         .send(TurnSearchPayload {
                             col: cur_pl,
-                            board,
-                            steps,
-                            depth,
+                            board: game.board(),
+                            steps: game.turns_count(),
+                            budget,
                         })
                         .unwrap();
                     waiting_for_turn = true;
                 }
 
-                if let Ok(turn) = turn_reciver.recv_timeout(std::time::Duration::from_millis(12)) {
-                    steps += 1;
-                    eval_str = format!(
-                        "Eval({eval_color}): {}",
-                        board.deep_evaluate_for(eval_color, MAX_EVAL_DEPTH, steps)
-                    );
-                    turn_str = format!("turn {steps}");
+                if let Ok(result) = turn_reciver.recv_timeout(std::time::Duration::from_millis(12)) {
+                    waiting_for_turn = false;
+                    match result.best_turn() {
+                        Some(turn) => {
+                            expected_line = result.principal_variation[1..].to_vec();
 
-                    moved = Some((cur_pl, turn, board, 0));
+                            let board = game.board();
+                            game.play(turn).expect("engine turn is legal");
+                            eval_str = format!(
+                                "Eval({eval_color}): {}",
+                                board.deep_evaluate_with(eval_color, rules, MAX_EVAL_DEPTH, game.turns_count())
+                            );
+                            turn_str = format!("turn {}", game.turns_count());
 
-                    board = board.perform_turn(turn, cur_pl);
-                    cur_pl = cur_pl.opposite();
-                    delta = 0.;
-                    waiting_for_turn = false;
-                } else {
+                            moved = Some((cur_pl, turn, board, 0));
+                            delta = 0.;
+                        }
+                        None => {
+                            // blocked engine can't continue, the game stops here
+                            eval_str = format!("{cur_pl} has no turns");
+                            turn_str.clear();
+                            finished = true;
+                        }
+                    }
                 }
             }
         } else if !finished && moved.is_none() {
-            eval_str = format!("finished in {steps} turns");
+            eval_str = match game.winner() {
+                Some(GameOutcome::Win(winner)) => {
+                    format!("{winner} won in {} turns", game.turns_count())
+                }
+                _ => format!("draw in {} turns", game.turns_count()),
+            };
             turn_str.clear();
             finished = true;
         }
@@ -150,8 +244,8 @@ fn main() {
 
         d.clear_background(Color::WHITE);
 
-        for ix in 0..8 {
-            for iy in 0..8 {
+        for ix in 0..width {
+            for iy in 0..height {
                 let col = if (ix ^ iy) & 1 != 0 {
                     Color::GRAY
                 } else {
@@ -164,18 +258,19 @@ fn main() {
         // TODO option to "mirror" positions for x & y axes
         let displaypos = |pos: Position| {
                 let (x, y) = (pos.get_x() as f32 * 64., pos.get_y() as f32 * 64.);
-                let x = if flip_x { 7. * 64. - x } else { x }; 
-                let y = if flip_y { 7. * 64. - y } else { y }; 
+                let x = if flip_x { (width - 1) as f32 * 64. - x } else { x }; 
+                let y = if flip_y { (height - 1) as f32 * 64. - y } else { y }; 
                 Vector2::new(x, y)
             };
         let displaypos_i = |pos: Position| {
             let (x, y) = (pos.get_x() as i32 * 64, pos.get_y() as i32 * 64);
-            let x = if flip_x { 7 * 64 - x } else { x }; 
-            let y = if flip_y { 7 * 64 - y } else { y }; 
+            let x = if flip_x { (width - 1) * 64 - x } else { x }; 
+            let y = if flip_y { (height - 1) * 64 - y } else { y }; 
             (x, y)
         };
 
         // draw all static pieces
+        let board = game.board();
         for pos in board.0[0].positions_iter() {
             if !matches!(moved, Some((PieceColor::Black, turn, _, _)) if turn.to == pos) {
                 d.draw_texture_ex(&checker_white, displaypos(pos), 0., 0.5, Color::DARKGRAY);
@@ -194,47 +289,44 @@ fn main() {
             } else {
                 Color::DARKGRAY
             };
-            let mut visited = false;
+            let turns = board
+                .path_for_with(turn, rules)
+                .expect("performed turn has a path")
+                .steps()
+                .collect::<Vec<_>>();
             let mut ended = false;
 
-            board.generate_all_turns_seqences_for_pos(col, turn.from, &mut |turns: &[Turn]| {
-                if !matches!(turns.last(), Some(turn2) if turn2.to == turn.to) || visited {
-                    return;
-                }
-                visited = true;
-
-                let mut t = (delta / ANIMATION_STEP).min(1.);
-                let mut i = *i;
+            let mut t = (delta / ANIMATION_STEP).min(1.);
+            let mut step = *i;
 
-                if i >= turns.len() {
-                    ended = true;
-                    i = turns.len() - 1;
-                    t = 1.;
-                }
-
-                let from = displaypos(turns[i].from);
-                let to = displaypos(turns[i].to);
+            if step >= turns.len() {
+                ended = true;
+                step = turns.len() - 1;
+                t = 1.;
+            }
 
-                d.draw_texture_ex(
-                    &checker_white,
-                    from + (to - from) * t + Vector2::new(16. * t * (1. - t), -64. * t * (1. - t)),
-                    0.,
-                    0.5,
-                    fill_col,
+            let from = displaypos(turns[step].from);
+            let to = displaypos(turns[step].to);
+
+            d.draw_texture_ex(
+                &checker_white,
+                from + (to - from) * t + Vector2::new(16. * t * (1. - t), -64. * t * (1. - t)),
+                0.,
+                0.5,
+                fill_col,
+            );
+
+            for turn in turns {
+                let from = displaypos(turn.from);
+                let to = displaypos(turn.to);
+
+                d.draw_line_ex(
+                    from + Vector2::new(32., 32.),
+                    to + Vector2::new(32., 32.),
+                    5.,
+                    Color::BLUE,
                 );
-
-                for turn in turns {
-                    let from = displaypos(turn.from);
-                    let to = displaypos(turn.to);
-
-                    d.draw_line_ex(
-                        from + Vector2::new(32., 32.),
-                        to + Vector2::new(32., 32.),
-                        5.,
-                        Color::BLUE,
-                    );
-                }
-            });
+            }
 
             if delta > ANIMATION_STEP {
                 delta = 0.;
@@ -246,15 +338,28 @@ fn main() {
             }
         }
 
-        // display possible moves
-        if let Some((col, pos)) = picked {
-            board.generate_all_turns_seqences_for_pos(col, pos, &mut |turns: &[Turn]| {
-                let last = turns.last().unwrap();
+        // display continuation expected by the engine
+        if moved.is_none() {
+            for turn in &expected_line {
+                let from = displaypos(turn.from);
+                let to = displaypos(turn.to);
+                d.draw_line_ex(
+                    from + Vector2::new(32., 32.),
+                    to + Vector2::new(32., 32.),
+                    3.,
+                    Color::ORANGE.fade(0.5),
+                );
+            }
+        }
 
-                let (x, y) = displaypos_i(last.to);
+        // display possible moves
+        if let Some((_, pos)) = picked {
+            for to in board.generate_turns_for_pos_with(pos, rules).positions_iter() {
+                let (x, y) = displaypos_i(to);
                 d.draw_rectangle(x, y, 64, 64, Color::BLUE.fade(0.3));
 
-                for turn in turns {
+                let path = board.path_for_with(Turn { from: pos, to }, rules).expect("turn is reachable");
+                for turn in path.steps() {
                     let from = displaypos(turn.from);
                     let to = displaypos(turn.to);
                     d.draw_line_ex(
@@ -264,11 +369,11 @@ fn main() {
                         Color::GREEN,
                     );
                 }
-            });
+            }
         }
 
-        d.draw_text(&eval_str, 12, 64 * 8 + 6, 20, Color::RED);
-        d.draw_text(&turn_str, 12 + 64 * 6, 64 * 8 + 6, 20, Color::RED);
+        d.draw_text(&eval_str, 12, 64 * height + 6, 20, Color::RED);
+        d.draw_text(&turn_str, 12 + 64 * (width - 2), 64 * height + 6, 20, Color::RED);
 
         if waiting_for_turn {
             d.draw_text("searching...", 12, 6, 60, Color::BLUE);
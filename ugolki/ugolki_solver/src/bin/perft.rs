@@ -0,0 +1,57 @@
+//! Turns generator counters
+//!
+//! Usage: `perft <depth> [board [rules]]` prints perft of the initial position divided by
+//! root turns (board is one of `6x6/4`, `8x8/9`, `8x8/12`, `10x10/16`, rules are one of
+//! `classic`, `diagonal`, `jumps-only`, `opponent-jumps`, classic ones by default),
+//! `perft --verify <max depth>` checks the reference positions.
+
+use std::process::ExitCode;
+use ugolki_solver::perft::{perft_divide_with, verify_references};
+use ugolki_solver::{BoardConfig, BoardPosition, PieceColor, RuleSet};
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (verify, depth, board, rules) = match args.as_slice() {
+        [flag, depth] if flag == "--verify" => (true, depth, None, None),
+        [depth] => (false, depth, None, None),
+        [depth, board] => (false, depth, Some(board), None),
+        [depth, board, rules] => (false, depth, Some(board), Some(rules)),
+        _ => (false, &String::new(), None, None),
+    };
+    let config = board.map_or(Some(&BoardConfig::CLASSIC), |name| {
+        BoardConfig::from_name(name)
+    });
+    let rules = rules.map_or(Some(RuleSet::CLASSIC), |name| RuleSet::from_name(name));
+    let (Ok(depth), Some(config), Some(rules)) = (depth.parse::<usize>(), config, rules) else {
+        eprintln!("usage: perft <depth> [board [rules]] | perft --verify <max depth>");
+        return ExitCode::FAILURE;
+    };
+
+    if verify {
+        return match verify_references(depth) {
+            Ok(()) => {
+                println!("all reference counts match");
+                ExitCode::SUCCESS
+            }
+            Err(mismatch) => {
+                eprintln!("{mismatch}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let divide = perft_divide_with(
+        BoardPosition::initial(config),
+        PieceColor::White,
+        rules,
+        depth,
+    );
+    for (turn, count) in &divide {
+        println!("{turn}: {count}");
+    }
+    println!(
+        "total: {}",
+        divide.iter().map(|(_, count)| count).sum::<u64>()
+    );
+    ExitCode::SUCCESS
+}
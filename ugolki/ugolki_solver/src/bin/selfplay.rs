@@ -0,0 +1,208 @@
+//! Plays two searcher configurations against each other
+//!
+//! Usage: `selfplay [options]`, options of the players are prefixed with `a-` or `b-`:
+//!
+//! - `--{a,b}-depth <depth>` fixed search depth (default 3)
+//! - `--{a,b}-time <ms>` time per turn instead of fixed depth
+//! - `--{a,b}-threads <n>` search threads (default 1)
+//! - `--{a,b}-weights <file>` evaluation weights file
+//! - `--{a,b}-draw-score <score>` score of the draw for the player (default 0)
+//! - `--board <board>` one of `6x6/4`, `8x8/9` (default), `8x8/12`, `10x10/16`
+//! - `--rules <rules>` one of `classic` (default), `diagonal`, `jumps-only`, `opponent-jumps`
+//! - `--stalling <policy>` one of `classic` (default), `official`, `unlimited`
+//! - `--equalising` black gets the equalising turn after white fills the camp
+//! - `--plies <n>` start from all positions after `n` turns (default 2)
+//! - `--pairs <n>` play at most `n` game pairs
+//! - `--max-turns <n>` turns count when the game is drawn (default 300)
+//! - `--elo0 <elo>`, `--elo1 <elo>` SPRT hypotheses (default 0 and 10)
+//! - `--games <file>` write played games in the tuning format (see `ugolki_solver::tuning`)
+//! - `--pgn <file>` write played games in portable game notation
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use ugolki_solver::eval::EvalWeights;
+use ugolki_solver::pgn;
+use ugolki_solver::selfplay::{Match, Player, SearchLimit, Sprt, StartPosition};
+use ugolki_solver::tuning::write_game;
+use ugolki_solver::{BoardConfig, GameOutcome, RuleSet, StallingPolicy, TurnSearcher};
+
+const DEFAULT_DEPTH: usize = 3;
+const DEFAULT_PLIES: usize = 2;
+const DEFAULT_MAX_TURNS: usize = 300;
+
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn parse_option<T: std::str::FromStr>(args: &[String], name: &str) -> Result<Option<T>, String> {
+    option(args, name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("invalid value of {name}"))
+        })
+        .transpose()
+}
+
+fn player(args: &[String], prefix: &str, rules: RuleSet) -> Result<Player, String> {
+    let mut searcher = TurnSearcher::new();
+    searcher.set_rules(rules);
+    if let Some(threads) = parse_option(args, &format!("--{prefix}-threads"))? {
+        searcher.set_threads(threads);
+    }
+    if let Some(path) = option(args, &format!("--{prefix}-weights")) {
+        let weights = File::open(path)
+            .and_then(EvalWeights::read_from)
+            .map_err(|err| format!("{path}: {err}"))?;
+        searcher.set_evaluator(Arc::new(weights));
+    }
+    if let Some(score) = parse_option(args, &format!("--{prefix}-draw-score"))? {
+        searcher.set_draw_score(score);
+    }
+
+    let limit = match parse_option(args, &format!("--{prefix}-time"))? {
+        Some(ms) => SearchLimit::Time(Duration::from_millis(ms)),
+        None => SearchLimit::Depth(
+            parse_option(args, &format!("--{prefix}-depth"))?.unwrap_or(DEFAULT_DEPTH),
+        ),
+    };
+    Ok(Player::new(searcher, limit))
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let mut rules = match option(args, "--rules") {
+        Some(name) => RuleSet::from_name(name).ok_or("unknown rules")?,
+        None => RuleSet::CLASSIC,
+    };
+    if let Some(name) = option(args, "--stalling") {
+        rules.stalling = StallingPolicy::from_name(name).ok_or("unknown stalling policy")?;
+    }
+    rules.equalising_move = args.iter().any(|arg| arg == "--equalising");
+    let mut first = player(args, "a", rules)?;
+    let mut second = player(args, "b", rules)?;
+
+    let config = match option(args, "--board") {
+        Some(name) => BoardConfig::from_name(name).ok_or("unknown board")?,
+        None => &BoardConfig::CLASSIC,
+    };
+    let mut starts = StartPosition::openings(
+        config,
+        rules,
+        parse_option(args, "--plies")?.unwrap_or(DEFAULT_PLIES),
+    );
+    if let Some(pairs) = parse_option(args, "--pairs")? {
+        starts.truncate(pairs);
+    }
+    let defaults = Sprt::default();
+    let sprt = Sprt {
+        elo0: parse_option(args, "--elo0")?.unwrap_or(defaults.elo0),
+        elo1: parse_option(args, "--elo1")?.unwrap_or(defaults.elo1),
+        ..defaults
+    };
+    let mut games = option(args, "--games")
+        .map(|path| File::create(path).map(BufWriter::new))
+        .transpose()
+        .map_err(|err| err.to_string())?;
+    let mut pgn_games = option(args, "--pgn")
+        .map(|path| File::create(path).map(BufWriter::new))
+        .transpose()
+        .map_err(|err| err.to_string())?;
+    let date = pgn::date(SystemTime::now());
+    let limits = [first.limit, second.limit];
+
+    let settings = Match {
+        starts,
+        max_turns: parse_option(args, "--max-turns")?.unwrap_or(DEFAULT_MAX_TURNS),
+        sprt: Some(sprt),
+    };
+    eprintln!("{} game pairs", settings.starts.len());
+
+    let result = settings.run(&mut first, &mut second, |record, result| {
+        let outcome = match record.outcome {
+            GameOutcome::Win(winner) => format!("{winner} won"),
+            GameOutcome::Draw => "draw".to_owned(),
+        };
+        println!(
+            "game {}: {outcome} in {} turns, +{} -{} ={}",
+            result.games(),
+            record.turns.len(),
+            result.wins,
+            result.losses,
+            result.draws
+        );
+        if let Some(games) = &mut games {
+            // tuning format starts from the initial position
+            if record.start.opening.len() == record.start.turns_count {
+                let turns = [record.start.opening.as_slice(), &record.turns].concat();
+                write_game(&mut *games, config, &turns).expect("failed to write game");
+            }
+        }
+        if let Some(pgn_games) = &mut pgn_games {
+            // games of each pair are played with `a` as white first
+            let (white, black) = if result.games() % 2 == 1 {
+                (0, 1)
+            } else {
+                (1, 0)
+            };
+            let time_control = if limits[0] == limits[1] {
+                limits[0].to_string()
+            } else {
+                format!("{} / {}", limits[white], limits[black])
+            };
+
+            let mut game = record.pgn(rules);
+            game.set_tag("Event", "selfplay");
+            game.set_tag("Date", date.as_str());
+            game.set_tag("Round", result.games().to_string());
+            game.set_tag("White", ["a", "b"][white]);
+            game.set_tag("Black", ["a", "b"][black]);
+            game.set_tag("TimeControl", time_control);
+            writeln!(pgn_games, "{game}").expect("failed to write game");
+        }
+    });
+
+    for games in [&mut games, &mut pgn_games].into_iter().flatten() {
+        games.flush().map_err(|err| err.to_string())?;
+    }
+
+    println!(
+        "games {}: +{} -{} ={}, score {:.3}",
+        result.games(),
+        result.wins,
+        result.losses,
+        result.draws,
+        result.score()
+    );
+    match result.elo() {
+        Some((elo, error)) => println!("elo {elo:.1} +- {error:.1}"),
+        None => println!("elo unbounded"),
+    }
+    println!(
+        "sprt [{}, {}]: llr {:.2} ({:.2}, {:.2}), {:?}",
+        sprt.elo0,
+        sprt.elo1,
+        result.llr(&sprt),
+        sprt.lower_bound(),
+        sprt.upper_bound(),
+        result.sprt(&sprt)
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
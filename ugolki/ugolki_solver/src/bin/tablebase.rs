@@ -0,0 +1,55 @@
+//! Endgame tablebase generator
+//!
+//! Usage: `tablebase <max outside> <output file> [board [rules]]` solves positions with at
+//! most `max outside` pieces outside of the target camp for each side (board is one of
+//! `6x6/4`, `8x8/9`, `8x8/12`, `10x10/16`, rules are one of `classic`, `diagonal`,
+//! `jumps-only`, `opponent-jumps`, classic ones by default) and writes the tablebase file.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::process::ExitCode;
+use std::time::Instant;
+use ugolki_solver::tablebase::Tablebase;
+use ugolki_solver::{BoardConfig, RuleSet};
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (max_outside, path, board, rules) = match args.as_slice() {
+        [max_outside, path] => (max_outside, path, None, None),
+        [max_outside, path, board] => (max_outside, path, Some(board), None),
+        [max_outside, path, board, rules] => (max_outside, path, Some(board), Some(rules)),
+        _ => (&String::new(), &String::new(), None, None),
+    };
+    let config = board.map_or(Some(&BoardConfig::CLASSIC), |name| {
+        BoardConfig::from_name(name)
+    });
+    let rules = rules.map_or(Some(RuleSet::CLASSIC), |name| RuleSet::from_name(name));
+    let (Ok(max_outside), Some(config), Some(rules)) =
+        (max_outside.parse::<usize>(), config, rules)
+    else {
+        eprintln!("usage: tablebase <max outside> <output file> [board [rules]]");
+        return ExitCode::FAILURE;
+    };
+
+    let start = Instant::now();
+    let Some(tablebase) = Tablebase::generate(config, rules, max_outside) else {
+        eprintln!("tablebase with {max_outside} pieces outside is too large for {config} board");
+        eprintln!("usage: tablebase <max outside> <output file> [board [rules]]");
+        return ExitCode::FAILURE;
+    };
+    println!(
+        "{} positions, {} solved in {:.1?}",
+        tablebase.len(),
+        tablebase.solved_count(),
+        start.elapsed()
+    );
+
+    let written = File::create(path).and_then(|file| tablebase.write_to(BufWriter::new(file)));
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Fits evaluation weights to a corpus of played games
+//!
+//! Usage: `tune [options] <games file> <output weights file> [initial weights file] [max passes]`,
+//! games file is read as portable game notation if its name ends with `.pgn`, otherwise it's
+//! in the tuning format (see `ugolki_solver::tuning`). Only games of the chosen board and
+//! rules are used:
+//!
+//! - `--board <board>` one of `6x6/4`, `8x8/9` (default), `8x8/12`, `10x10/16`
+//! - `--rules <rules>` one of `classic` (default), `diagonal`, `jumps-only`, `opponent-jumps`
+//! - `--stalling <policy>` one of `classic` (default), `official`, `unlimited`
+//! - `--equalising` black gets the equalising turn after white fills the camp
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+use ugolki_solver::eval::EvalWeights;
+use ugolki_solver::tuning::Tuner;
+use ugolki_solver::{BoardConfig, RuleSet, StallingPolicy};
+
+const DEFAULT_MAX_PASSES: usize = 200;
+
+/// Options followed by a value
+const VALUE_OPTIONS: [&str; 3] = ["--board", "--rules", "--stalling"];
+
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Arguments which aren't options or their values
+fn positional(args: &[String]) -> Vec<&str> {
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if VALUE_OPTIONS.contains(&arg.as_str()) {
+            args.next();
+        } else if !arg.starts_with("--") {
+            positional.push(arg.as_str());
+        }
+    }
+    positional
+}
+
+fn tuner(args: &[String]) -> Result<Tuner, String> {
+    let config = match option(args, "--board") {
+        Some(name) => BoardConfig::from_name(name).ok_or("unknown board")?,
+        None => &BoardConfig::CLASSIC,
+    };
+    let mut rules = match option(args, "--rules") {
+        Some(name) => RuleSet::from_name(name).ok_or("unknown rules")?,
+        None => RuleSet::CLASSIC,
+    };
+    if let Some(name) = option(args, "--stalling") {
+        rules.stalling = StallingPolicy::from_name(name).ok_or("unknown stalling policy")?;
+    }
+    rules.equalising_move = args.iter().any(|arg| arg == "--equalising");
+    Ok(Tuner::with_config(config, rules))
+}
+
+fn run(mut tuner: Tuner, args: &[&str]) -> Result<(), String> {
+    let games_path = args[0];
+    let games = if games_path.ends_with(".pgn") {
+        let text = std::fs::read_to_string(games_path).map_err(|err| err.to_string())?;
+        tuner.read_pgn(&text).map_err(|err| err.to_string())?
+    } else {
+        let file = File::open(games_path).map_err(|err| err.to_string())?;
+        tuner
+            .read_games(BufReader::new(file))
+            .map_err(|err| err.to_string())?
+    };
+    eprintln!("{games} games, {} positions", tuner.len());
+
+    let weights = match args.get(2) {
+        Some(path) => File::open(path)
+            .and_then(EvalWeights::read_from)
+            .map_err(|err| format!("{path}: {err}"))?,
+        None => EvalWeights::DEFAULT,
+    };
+    let max_passes = args
+        .get(3)
+        .and_then(|passes| passes.parse().ok())
+        .unwrap_or(DEFAULT_MAX_PASSES);
+
+    tuner.fit_scale(&weights);
+    eprintln!(
+        "scale {:e}, error {:.6}",
+        tuner.scale(),
+        tuner.error(&weights)
+    );
+
+    let weights = tuner.tune(weights, max_passes);
+    eprintln!("tuned error {:.6}", tuner.error(&weights));
+
+    File::create(args[1])
+        .and_then(|file| weights.write_to(file))
+        .map_err(|err| format!("{}: {err}", args[1]))
+}
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let positional = positional(&args);
+    if positional.len() < 2 {
+        eprintln!(
+            "usage: tune [options] <games file> <output weights file> [initial weights file] [max passes]"
+        );
+        return ExitCode::FAILURE;
+    }
+
+    match tuner(&args).and_then(|tuner| run(tuner, &positional)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Opening book: weighted candidate turns for known positions
+//!
+//! Book is built with [`OpeningBookBuilder`] from deep searches of early positions or
+//! from recorded games, saved to a file and later given to
+//! [`TurnSearcher::set_book`](crate::TurnSearcher::set_book).
+//!
+//! File format (all integers are little endian):
+//!
+//! | bytes | content                                      |
+//! |-------|----------------------------------------------|
+//! | 4     | magic `UGOB`                                 |
+//! | 1     | format version (2)                           |
+//! | 3     | reserved (0)                                 |
+//! | 4     | number of positions                          |
+//!
+//! followed by the positions (sorted by pieces, board and side to move):
+//!
+//! | bytes | content                                      |
+//! |-------|----------------------------------------------|
+//! | 4     | board width, height, camp width and height   |
+//! | 16    | black pieces mask                            |
+//! | 16    | white pieces mask                            |
+//! | 1     | side to move (0 - black, 1 - white)          |
+//! | 1     | number of turns                              |
+//! | 6 * n | turns: `from`, `to` (1 byte each), weight (4 bytes) |
+//!
+//! Turns of each position are sorted by weight, heaviest first.
+
+use crate::{
+    BoardConfig, BoardPosition, PieceColor, Position, PositionMask, RuleSet, Turn, TurnSearcher,
+};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"UGOB";
+const VERSION: u8 = 2;
+const HEADER_LEN: usize = 12;
+
+/// Candidate turn with its relative weight
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BookTurn {
+    pub turn: Turn,
+    pub weight: u32,
+}
+
+/// Positions with candidate turns, see [module docs](self)
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBook {
+    positions: BTreeMap<(BoardPosition, PieceColor), Vec<BookTurn>>,
+}
+
+impl OpeningBook {
+    /// Number of positions in the book
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Candidate turns for `side_to_move`, heaviest first (empty if position isn't in the book)
+    #[must_use]
+    #[inline]
+    pub fn turns(&self, board: BoardPosition, side_to_move: PieceColor) -> &[BookTurn] {
+        self.positions
+            .get(&(board, side_to_move))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Picks one of the candidate turns with probability proportional to its weight
+    ///
+    /// `random` is any uniformly distributed number, same one gives the same turn.
+    #[must_use]
+    pub fn choose(
+        &self,
+        board: BoardPosition,
+        side_to_move: PieceColor,
+        random: u64,
+    ) -> Option<Turn> {
+        let turns = self.turns(board, side_to_move);
+        let total = turns.iter().map(|turn| turn.weight as u64).sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut point = random % total;
+        turns.iter().find_map(|turn| {
+            if point < turn.weight as u64 {
+                Some(turn.turn)
+            } else {
+                point -= turn.weight as u64;
+                None
+            }
+        })
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4] = VERSION;
+        header[8..].copy_from_slice(&(self.positions.len() as u32).to_le_bytes());
+        writer.write_all(&header)?;
+
+        for ((board, side), turns) in &self.positions {
+            let config = board.config();
+            writer.write_all(&[
+                config.width,
+                config.height,
+                config.camp_width,
+                config.camp_height,
+            ])?;
+            writer.write_all(&board.0[0].0.to_le_bytes())?;
+            writer.write_all(&board.0[1].0.to_le_bytes())?;
+            writer.write_all(&[*side as u8, turns.len() as u8])?;
+            for BookTurn { turn, weight } in turns {
+                writer.write_all(&[turn.from.0, turn.to.0])?;
+                writer.write_all(&weight.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not an opening book file"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid("unsupported opening book version"));
+        }
+        let count = u32::from_le_bytes(header[8..].try_into().unwrap());
+
+        let mut positions = BTreeMap::new();
+        for _ in 0..count {
+            let mut position = [0; 38];
+            reader.read_exact(&mut position)?;
+            let config = BoardConfig::find(position[0], position[1], position[2], position[3])
+                .ok_or_else(|| invalid("unknown board"))?;
+            let board = BoardPosition(
+                [
+                    PositionMask(u128::from_le_bytes(position[4..20].try_into().unwrap())),
+                    PositionMask(u128::from_le_bytes(position[20..36].try_into().unwrap())),
+                ],
+                config,
+            );
+            let side = match position[36] {
+                0 => PieceColor::Black,
+                1 => PieceColor::White,
+                _ => return Err(invalid("invalid side to move")),
+            };
+            if !board.is_valid_position() {
+                return Err(invalid("invalid position"));
+            }
+
+            let mut turns = Vec::with_capacity(position[37] as usize);
+            for _ in 0..position[37] {
+                let mut turn = [0; 6];
+                reader.read_exact(&mut turn)?;
+                if !config.contains(Position(turn[0])) || !config.contains(Position(turn[1])) {
+                    return Err(invalid("invalid turn"));
+                }
+                turns.push(BookTurn {
+                    turn: Turn {
+                        from: Position(turn[0]),
+                        to: Position(turn[1]),
+                    },
+                    weight: u32::from_le_bytes(turn[2..].try_into().unwrap()),
+                });
+            }
+            positions.insert((board, side), turns);
+        }
+
+        Ok(Self { positions })
+    }
+}
+
+/// Collects turns for [`OpeningBook`], weights of repeated turns are summed up
+#[derive(Debug, Clone, Default)]
+pub struct OpeningBookBuilder {
+    positions: BTreeMap<(BoardPosition, PieceColor), BTreeMap<Turn, u32>>,
+}
+
+impl OpeningBookBuilder {
+    #[must_use]
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `weight` to the `turn` of `side_to_move` (turn must be legal in that position
+    /// under the rules the book is used with)
+    pub fn add_turn(
+        &mut self,
+        board: BoardPosition,
+        side_to_move: PieceColor,
+        turn: Turn,
+        weight: u32,
+    ) {
+        debug_assert!(board.0[side_to_move as usize].has_piece_at(turn.from));
+        let entry = self
+            .positions
+            .entry((board, side_to_move))
+            .or_default()
+            .entry(turn)
+            .or_default();
+        *entry = entry.saturating_add(weight);
+    }
+
+    /// Adds first `max_plies` turns of the game played from the initial position of `config`
+    /// board under `rules`
+    ///
+    /// Returns number of turns added, the record is cut at the first illegal turn
+    /// or when the game is finished.
+    pub fn add_game(
+        &mut self,
+        config: &'static BoardConfig,
+        rules: RuleSet,
+        turns: &[Turn],
+        max_plies: usize,
+    ) -> usize {
+        let mut board = BoardPosition::initial(config);
+        let mut side = PieceColor::White;
+        for (i, turn) in turns.iter().take(max_plies).enumerate() {
+            if board.has_winner_with(i, rules) || !board.is_valid_turn_with(*turn, side, rules) {
+                return i;
+            }
+            self.add_turn(board, side, *turn, 1);
+            board = board.perform_turn(*turn, side);
+            side = side.opposite();
+        }
+        turns.len().min(max_plies)
+    }
+
+    /// Adds the searched best turn of every position of `config` board reachable in less
+    /// than `plies` turns under the searcher's rules
+    ///
+    /// Each position is searched to `max_depth` and added once, however many ways lead
+    /// to it. Number of searches grows exponentially with `plies`, so keep it small.
+    pub fn add_searched(
+        &mut self,
+        searcher: &mut TurnSearcher,
+        config: &'static BoardConfig,
+        max_depth: usize,
+        plies: usize,
+    ) {
+        self.add_searched_from(
+            searcher,
+            &mut BTreeMap::new(),
+            BoardPosition::initial(config),
+            PieceColor::White,
+            0,
+            max_depth,
+            plies,
+        );
+    }
+
+    /// `visited` keeps the most plies left any walk reached the position with, so
+    /// transpositions are searched once but still walked as far as the longest way allows
+    #[allow(clippy::too_many_arguments)]
+    fn add_searched_from(
+        &mut self,
+        searcher: &mut TurnSearcher,
+        visited: &mut BTreeMap<(BoardPosition, PieceColor), usize>,
+        board: BoardPosition,
+        side: PieceColor,
+        turns_count: usize,
+        max_depth: usize,
+        plies: usize,
+    ) {
+        if plies == 0 || board.has_winner_with(turns_count, searcher.rules()) {
+            return;
+        }
+        let walked = visited.get(&(board, side)).copied();
+        if walked.is_some_and(|walked| walked >= plies) {
+            return;
+        }
+        visited.insert((board, side), plies);
+        if walked.is_none() {
+            if let Some(turn) = searcher
+                .next_turn(board, side, max_depth, turns_count)
+                .best_turn()
+            {
+                self.add_turn(board, side, turn, 1);
+            }
+        }
+        for turn in board.generate_all_turns_for_with(side, searcher.rules()) {
+            self.add_searched_from(
+                searcher,
+                visited,
+                board.perform_turn(turn, side),
+                side.opposite(),
+                turns_count + 1,
+                max_depth,
+                plies - 1,
+            );
+        }
+    }
+
+    #[must_use]
+    pub fn build(self) -> OpeningBook {
+        let positions = self
+            .positions
+            .into_iter()
+            .map(|(key, turns)| {
+                let mut turns = turns
+                    .into_iter()
+                    .map(|(turn, weight)| BookTurn { turn, weight })
+                    .collect::<Vec<_>>();
+                turns.sort_by_key(|turn| std::cmp::Reverse(turn.weight));
+                // file stores turns count in a single byte
+                turns.truncate(u8::MAX as usize);
+                (key, turns)
+            })
+            .collect();
+        OpeningBook { positions }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn turn(text: &str) -> Turn {
+        text.parse().unwrap()
+    }
+
+    fn sample_book() -> OpeningBook {
+        let mut builder = OpeningBookBuilder::new();
+        let config = &BoardConfig::CLASSIC;
+        for game in [
+            ["c3-c4", "f6-f5", "a3-a4"],
+            ["c3-c4", "f6-f5", "b3-b4"],
+            ["c3-c4", "f6-e6", "a3-a4"],
+            ["a3-a4", "f6-f5", "a4-a5"],
+        ] {
+            let turns = game.map(turn);
+            assert_eq!(builder.add_game(config, RuleSet::CLASSIC, &turns, 2), 2);
+        }
+        // illegal turn ends the record
+        let illegal = [turn("c3-c4"), turn("c4-c6")];
+        assert_eq!(builder.add_game(config, RuleSet::CLASSIC, &illegal, 2), 1);
+        builder.build()
+    }
+
+    #[test]
+    fn turns_are_weighted() {
+        let book = sample_book();
+        let initial = BoardPosition::default();
+        assert_eq!(book.len(), 3);
+        assert_eq!(
+            book.turns(initial, PieceColor::White),
+            [
+                BookTurn {
+                    turn: turn("c3-c4"),
+                    weight: 4,
+                },
+                BookTurn {
+                    turn: turn("a3-a4"),
+                    weight: 1,
+                },
+            ]
+        );
+        assert!(book.turns(initial, PieceColor::Black).is_empty());
+        assert_eq!(
+            book.choose(initial, PieceColor::White, 3),
+            Some(turn("c3-c4"))
+        );
+        assert_eq!(
+            book.choose(initial, PieceColor::White, 4),
+            Some(turn("a3-a4"))
+        );
+        assert_eq!(book.choose(initial, PieceColor::Black, 4), None);
+    }
+
+    #[test]
+    fn searched_positions_are_added_once() {
+        let config = &BoardConfig::SMALL;
+        let mut searcher = TurnSearcher::new();
+        let mut builder = OpeningBookBuilder::new();
+        builder.add_searched(&mut searcher, config, 2, 5);
+        let book = builder.build();
+
+        // positions reachable in less than 5 turns, many of them in several ways
+        let mut positions = BTreeSet::new();
+        let mut walks = 0;
+        let mut layer = vec![(BoardPosition::initial(config), PieceColor::White)];
+        for _ in 0..5 {
+            walks += layer.len();
+            positions.extend(layer.iter().copied());
+            layer = layer
+                .iter()
+                .flat_map(|&(board, side)| {
+                    board
+                        .generate_all_turns_for(side)
+                        .map(move |turn| (board.perform_turn(turn, side), side.opposite()))
+                })
+                .collect();
+        }
+        assert!(positions.len() < walks);
+        assert_eq!(book.len(), positions.len());
+        for (board, side) in positions {
+            assert_eq!(book.turns(board, side).len(), 1);
+            assert_eq!(book.turns(board, side)[0].weight, 1);
+        }
+    }
+
+    #[test]
+    fn transpositions_share_weights() {
+        let config = &BoardConfig::CLASSIC;
+        let mut builder = OpeningBookBuilder::new();
+        for game in [
+            ["c3-c4", "f6-f5", "a3-a4", "f5-f4"],
+            ["a3-a4", "f6-f5", "c3-c4", "f5-f4"],
+            ["a3-a4", "f6-f5", "c3-c4", "g6-g5"],
+        ] {
+            let turns = game.map(turn);
+            assert_eq!(builder.add_game(config, RuleSet::CLASSIC, &turns, 4), 4);
+        }
+        let book = builder.build();
+
+        let board = ["c3-c4", "f6-f5", "a3-a4"]
+            .into_iter()
+            .zip([PieceColor::White, PieceColor::Black, PieceColor::White])
+            .fold(BoardPosition::initial(config), |board, (text, side)| {
+                board.perform_turn(turn(text), side)
+            });
+        let expected = [
+            BookTurn {
+                turn: turn("f5-f4"),
+                weight: 2,
+            },
+            BookTurn {
+                turn: turn("g6-g5"),
+                weight: 1,
+            },
+        ];
+        assert_eq!(book.turns(board, PieceColor::Black), expected);
+
+        // weights and their order survive the file
+        let mut bytes = Vec::new();
+        book.write_to(&mut bytes).unwrap();
+        let read = OpeningBook::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(read.len(), book.len());
+        assert_eq!(read.turns(board, PieceColor::Black), expected);
+    }
+
+    #[test]
+    fn invalid_positions_and_turns() {
+        let mut bytes = Vec::new();
+        sample_book().write_to(&mut bytes).unwrap();
+        // fields of the first position
+        let black_offset = HEADER_LEN + 4;
+        let white_offset = black_offset + 16;
+        let side_offset = white_offset + 16;
+        let turn_offset = side_offset + 2;
+        for (offset, byte, message) in [
+            (HEADER_LEN, 7, "unknown board"),
+            (side_offset, 2, "invalid side to move"),
+            // white piece on the black one's square
+            (
+                white_offset + 8,
+                bytes[black_offset + 8],
+                "invalid position",
+            ),
+            (turn_offset, Position::COUNT as u8, "invalid turn"),
+        ] {
+            let mut bytes = bytes.clone();
+            bytes[offset] |= byte;
+            let err = OpeningBook::read_from(bytes.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{offset}");
+            assert_eq!(err.to_string(), message);
+        }
+    }
+}
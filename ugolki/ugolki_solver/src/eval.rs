@@ -0,0 +1,392 @@
+//! Static evaluation of positions which aren't finished yet
+
+use crate::{BoardPosition, PieceColor, Position, RuleSet, Turn};
+use std::io::{self, Read, Write};
+
+/// Pieces of a single side on the largest possible board (half of it)
+const MAX_PIECES: usize = Position::COUNT / 2;
+
+/// Heuristic score of unfinished position used by the search
+///
+/// Implementations are shared between search threads.
+pub trait Evaluator: std::fmt::Debug + Send + Sync {
+    /// Score of `board` for `player_color` in the game under `rules`, higher is better for
+    /// the player
+    ///
+    /// Called only for positions without a winner. Score for the other player
+    /// is expected to be the negated one, so `i32::MIN` is never returned.
+    fn evaluate(
+        &self,
+        board: BoardPosition,
+        rules: RuleSet,
+        player_color: PieceColor,
+        turns_count: usize,
+    ) -> i32;
+}
+
+/// Hand-made heuristic with [`EvalWeights::DEFAULT`]: distance to the target camp,
+/// shape of the pieces and penalty for pieces left in the starting camp
+#[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DefaultEvaluator;
+
+impl Evaluator for DefaultEvaluator {
+    #[inline]
+    fn evaluate(
+        &self,
+        board: BoardPosition,
+        rules: RuleSet,
+        player_color: PieceColor,
+        turns_count: usize,
+    ) -> i32 {
+        EvalWeights::DEFAULT.evaluate(board, rules, player_color, turns_count)
+    }
+}
+
+/// Weights of the [`DefaultEvaluator`] heuristic, evaluator itself
+///
+/// Stored as a text file with `name = value` line for each weight, see
+/// [`tuning`](crate::tuning) to fit them against played games.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EvalWeights {
+    /// Turns count when positional weight switches from the whole shape to the worst piece
+    pub phase_turns: i32,
+    /// Positional weight (all pieces and the worst one) before `phase_turns`
+    pub early_positional: i32,
+    /// Positional weight (the worst piece) after `phase_turns`
+    pub late_positional: i32,
+    /// Distance to the target camp while pieces are left in the starting one
+    pub camp_distance: i32,
+    /// Penalty for squared number of pieces left in the starting camp, grows every turn
+    pub starter_penalty: i32,
+    /// Turns before `starter_penalty` starts to grow
+    pub starter_grace_turns: i32,
+    /// Distance to the target camp after leaving the starting one
+    pub distance: i32,
+    /// Length of the best turns
+    pub mobility: i32,
+    /// Penalty for the farthest row and column
+    pub tail_penalty: i32,
+}
+
+impl EvalWeights {
+    pub const DEFAULT: Self = Self {
+        phase_turns: 30,
+        early_positional: 70,
+        late_positional: 20,
+        camp_distance: 200,
+        starter_penalty: 800,
+        starter_grace_turns: 10,
+        distance: 700,
+        mobility: 30,
+        tail_penalty: 15,
+    };
+
+    pub const NAMES: [&'static str; 9] = [
+        "phase_turns",
+        "early_positional",
+        "late_positional",
+        "camp_distance",
+        "starter_penalty",
+        "starter_grace_turns",
+        "distance",
+        "mobility",
+        "tail_penalty",
+    ];
+
+    /// Weights in order of [`NAMES`](Self::NAMES)
+    #[must_use]
+    #[inline]
+    pub fn values(&self) -> [i32; 9] {
+        [
+            self.phase_turns,
+            self.early_positional,
+            self.late_positional,
+            self.camp_distance,
+            self.starter_penalty,
+            self.starter_grace_turns,
+            self.distance,
+            self.mobility,
+            self.tail_penalty,
+        ]
+    }
+
+    /// Mutable weights in order of [`NAMES`](Self::NAMES)
+    #[must_use]
+    #[inline]
+    pub fn values_mut(&mut self) -> [&mut i32; 9] {
+        [
+            &mut self.phase_turns,
+            &mut self.early_positional,
+            &mut self.late_positional,
+            &mut self.camp_distance,
+            &mut self.starter_penalty,
+            &mut self.starter_grace_turns,
+            &mut self.distance,
+            &mut self.mobility,
+            &mut self.tail_penalty,
+        ]
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        for (name, value) in Self::NAMES.iter().zip(self.values()) {
+            writeln!(writer, "{name} = {value}")?;
+        }
+        Ok(())
+    }
+
+    /// Reads weights file, weights missing in it keep default values
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut weights = Self::DEFAULT;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("line {}: expected `name = value`", i + 1)))?;
+            let index = Self::NAMES
+                .iter()
+                .position(|known| *known == name.trim())
+                .ok_or_else(|| invalid(format!("line {}: unknown weight", i + 1)))?;
+            *weights.values_mut()[index] = value
+                .trim()
+                .parse()
+                .map_err(|_| invalid(format!("line {}: invalid value", i + 1)))?;
+        }
+        Ok(weights)
+    }
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Evaluator for EvalWeights {
+    #[inline]
+    fn evaluate(
+        &self,
+        board: BoardPosition,
+        rules: RuleSet,
+        player_color: PieceColor,
+        turns_count: usize,
+    ) -> i32 {
+        let my_val = board.evaluation_helper_position_value(self, rules, player_color, turns_count);
+        let other_val = board.evaluation_helper_position_value(
+            self,
+            rules,
+            player_color.opposite(),
+            turns_count,
+        );
+
+        // extreme weights saturate, the score stays negatable
+        my_val.saturating_sub(other_val).max(-i32::MAX)
+    }
+}
+
+impl BoardPosition {
+    #[inline]
+    fn evaluation_helper_position_value(
+        self,
+        weights: &EvalWeights,
+        rules: RuleSet,
+        color: PieceColor,
+        turns_count: usize,
+    ) -> i32 {
+        let starter_pieces = self.starter_square_pieces(color);
+        let dist = self.distance_to_end_point(weights, color);
+        let (positional_w_all, positional_w_min) = self.positional_weight(color);
+
+        let positional_w = if turns_count < weights.phase_turns.max(0) as usize {
+            (positional_w_all + positional_w_min).saturating_mul(weights.early_positional)
+        } else {
+            positional_w_min.saturating_mul(weights.late_positional)
+        };
+
+        if self.starter_square_pieces(color) > 0 {
+            let grace_turns = weights.starter_grace_turns.max(0) as usize;
+            let penalty_turns = i32::try_from(turns_count.max(grace_turns) - grace_turns)
+                .unwrap_or(i32::MAX)
+                .saturating_mul(weights.starter_penalty);
+            0i32.saturating_sub(dist.saturating_mul(weights.camp_distance))
+                .saturating_sub(starter_pieces.pow(2).saturating_mul(penalty_turns))
+        } else {
+            positional_w
+                .saturating_sub(dist.saturating_mul(weights.distance))
+                .saturating_add(
+                    self.turns_weight(rules, color)
+                        .saturating_mul(weights.mobility),
+                )
+        }
+    }
+
+    #[inline]
+    fn distance_to_end_point(self, weights: &EvalWeights, player_color: PieceColor) -> i32 {
+        let config = self.config();
+        let mut pos_x = [0u8; MAX_PIECES];
+        let mut pos_y = [0u8; MAX_PIECES];
+        let pieces = self.0[player_color as usize].count() as usize;
+
+        // coordinates relative to the corner of the target camp
+        for (i, pos) in self.0[player_color as usize].positions_iter().enumerate() {
+            if player_color == PieceColor::Black {
+                pos_x[i] = pos.get_x();
+                pos_y[i] = pos.get_y();
+            } else {
+                pos_x[i] = config.width - 1 - pos.get_x();
+                pos_y[i] = config.height - 1 - pos.get_y();
+            }
+        }
+
+        let (pos_x, pos_y) = (&mut pos_x[..pieces], &mut pos_y[..pieces]);
+        pos_x.sort_unstable();
+        pos_y.sort_unstable();
+
+        let mut score = 0i32;
+
+        // filled camp has `camp_height` pieces in each column and `camp_width` in each row
+        for (i, x) in pos_x.iter().enumerate() {
+            score += (x.abs_diff(i as u8 / config.camp_height) as i32 + 1).pow(3) - 1;
+        }
+        for (i, y) in pos_y.iter().enumerate() {
+            score += (y.abs_diff(i as u8 / config.camp_width) as i32 + 1).pow(3) - 1;
+        }
+
+        let tail =
+            pos_x.last().copied().unwrap_or(0) as i32 + pos_y.last().copied().unwrap_or(0) as i32;
+        score.saturating_add(weights.tail_penalty.saturating_mul(tail))
+    }
+
+    #[inline]
+    fn positional_weight(self, player_color: PieceColor) -> (i32, i32) {
+        let config = self.config();
+        let (center_x, center_y) = (config.width as i32 / 2, config.height as i32 / 2);
+        let mut weight = 0;
+        let mut min_weight = 1000000;
+        for pos in self.0[player_color as usize].positions_iter() {
+            let (x, y) = if player_color == PieceColor::Black {
+                (
+                    center_x - 1 - pos.get_x() as i32,
+                    center_y - 1 - pos.get_y() as i32,
+                )
+            } else {
+                (pos.get_x() as i32 - center_x, pos.get_y() as i32 - center_y)
+            };
+
+            let cur_weight = (x.max(y).pow(3)) * 10 - ((x.abs_diff(y) as i32).max(3) - 3) * 100;
+
+            weight += cur_weight;
+            min_weight = min_weight.min(cur_weight);
+        }
+
+        (weight, min_weight)
+    }
+
+    /// Length of the best turn of each piece under `rules`
+    #[inline]
+    fn turns_weight(self, rules: RuleSet, player_color: PieceColor) -> i32 {
+        self.0[player_color as usize]
+            .positions_iter()
+            .map(|from| {
+                self.generate_turns_for_pos_with(from, rules)
+                    .positions_iter()
+                    .map(|to| Turn { from, to }.value_for(player_color) + 2)
+                    .max()
+                    .unwrap_or(0)
+                    .max(0)
+            })
+            .sum()
+    }
+
+    #[inline]
+    fn starter_square_pieces(self, player_color: PieceColor) -> i32 {
+        let starter_mask = self.config().starting_camp(player_color);
+
+        self.0[player_color as usize]
+            .positions_iter()
+            .map(|pos| starter_mask.has_piece_at(pos) as i32)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::random_positions;
+    use crate::BoardConfig;
+
+    #[test]
+    fn mobility_follows_the_rules() {
+        let positions = random_positions(&BoardConfig::CLASSIC, 200);
+        let still = EvalWeights {
+            mobility: 0,
+            ..EvalWeights::DEFAULT
+        };
+        let mut differs = false;
+        for board in positions {
+            let eval = |weights: &EvalWeights, rules| {
+                weights.evaluate(board, rules, PieceColor::White, 40)
+            };
+            assert_eq!(
+                eval(&still, RuleSet::CLASSIC),
+                eval(&still, RuleSet::DIAGONAL)
+            );
+            differs |= eval(&EvalWeights::DEFAULT, RuleSet::CLASSIC)
+                != eval(&EvalWeights::DEFAULT, RuleSet::DIAGONAL);
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn weights_file_round_trip() {
+        let mut weights = EvalWeights::DEFAULT;
+        for (i, value) in weights.values_mut().into_iter().enumerate() {
+            *value = i as i32 * 7 - 3;
+        }
+        let mut file = Vec::new();
+        weights.write_to(&mut file).unwrap();
+        assert_eq!(EvalWeights::read_from(file.as_slice()).unwrap(), weights);
+
+        let partial = "# comment\n\nmobility = 5\n";
+        let read = EvalWeights::read_from(partial.as_bytes()).unwrap();
+        assert_eq!(
+            read,
+            EvalWeights {
+                mobility: 5,
+                ..EvalWeights::DEFAULT
+            }
+        );
+        for invalid in ["mobility 5", "speed = 1", "mobility = x"] {
+            assert!(EvalWeights::read_from(invalid.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn extreme_weights_saturate() {
+        let positions = random_positions(&BoardConfig::CLASSIC, 50);
+        for extreme in [i32::MIN, -1_000_000, i32::MAX] {
+            let mut weights = EvalWeights::DEFAULT;
+            for value in weights.values_mut() {
+                *value = extreme;
+            }
+            for &board in &positions {
+                for turns_count in [0, 40, usize::MAX / 2] {
+                    let eval =
+                        |color| weights.evaluate(board, RuleSet::CLASSIC, color, turns_count);
+                    let white = eval(PieceColor::White);
+                    assert!(white > i32::MIN);
+                    assert_eq!(white, -eval(PieceColor::Black));
+                }
+            }
+        }
+    }
+}
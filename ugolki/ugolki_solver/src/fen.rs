@@ -0,0 +1,337 @@
+//! One-line text form of the position with side to move and turns count (like FEN in chess)
+//!
+//! Fields are separated by spaces: `<rows> <side to move> <turns count> [rules]`, e.g. the
+//! initial position of the classic board is `5BBB/5BBB/5BBB/8/8/WWW5/WWW5/WWW5 w 0`.
+//!
+//! - rows go from the top one (the highest rank, see [`notation`](crate::notation)) to rank 1
+//!   and are separated by `/`, `W` and `B` are the pieces, numbers are runs of free squares.
+//!   Board is recognised by its size and the number of pieces
+//! - side to move is `w` or `b`
+//! - turns count is in plies. It isn't checked against the side to move: set up positions
+//!   may start with either side (like the black to move
+//!   [`PERFT_REFERENCES`](crate::perft::PERFT_REFERENCES) one), while the count still
+//!   decides when the stalling policy applies
+//! - rules are omitted for [`RuleSet::CLASSIC`], otherwise it's `+` separated list of
+//!   differences from them: `diagonal`, `jumps-only`, `opponent-jumps`, `equalising` and
+//!   the stalling policy name (custom stalling policies aren't written)
+
+use crate::{
+    BoardConfig, BoardPosition, JumpOver, PieceColor, Position, PositionMask, RuleSet,
+    StallingPolicy,
+};
+use std::str::FromStr;
+
+/// Reason the position string can't be parsed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FenError {
+    /// Rows, side to move or turns count is missing
+    MissingField,
+    /// Something follows the rules
+    TooManyFields,
+    /// Row has unexpected character or its length differs from the other rows
+    InvalidRow,
+    /// None of [`BoardConfig::VARIANTS`] has such size and number of pieces
+    UnknownBoard,
+    /// Pieces don't make a valid position (see [`BoardPosition::is_valid_position`])
+    InvalidPosition,
+    /// Side to move isn't `w` or `b`
+    InvalidSide,
+    /// Turns count isn't a number
+    InvalidTurnsCount,
+    /// Unknown rule name
+    UnknownRules,
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::MissingField => "position should have rows, side to move and turns count",
+            Self::TooManyFields => "unexpected field after the rules",
+            Self::InvalidRow => "invalid row",
+            Self::UnknownBoard => "unsupported board size or number of pieces",
+            Self::InvalidPosition => "invalid position",
+            Self::InvalidSide => "side to move should be `w` or `b`",
+            Self::InvalidTurnsCount => "invalid turns count",
+            Self::UnknownRules => "unknown rules",
+        })
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Position with everything needed to continue the game from it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Fen {
+    pub board: BoardPosition,
+    pub side_to_move: PieceColor,
+    /// Plies made since the initial position, its parity may differ from the side to move
+    pub turns_count: usize,
+    pub rules: RuleSet,
+}
+
+impl Fen {
+    /// Initial position of `config` board under `rules`
+    #[must_use]
+    #[inline]
+    pub fn initial(config: &'static BoardConfig, rules: RuleSet) -> Self {
+        Self {
+            board: BoardPosition::initial(config),
+            side_to_move: PieceColor::White,
+            turns_count: 0,
+            rules,
+        }
+    }
+}
+
+impl std::fmt::Display for Fen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_rows(f, self.board)?;
+
+        let side = match self.side_to_move {
+            PieceColor::White => 'w',
+            PieceColor::Black => 'b',
+        };
+        write!(f, " {side} {}", self.turns_count)?;
+
+        if let Some(rules) = rules_name(self.rules) {
+            write!(f, " {rules}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Fen {
+    type Err = FenError;
+
+    fn from_str(s: &str) -> Result<Self, FenError> {
+        let mut fields = s.split_whitespace();
+        let (Some(rows), Some(side), Some(turns_count)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(FenError::MissingField);
+        };
+        let rules = fields.next();
+        if fields.next().is_some() {
+            return Err(FenError::TooManyFields);
+        }
+
+        let board = parse_rows(rows)?;
+
+        let side_to_move = match side {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::InvalidSide),
+        };
+        let turns_count = turns_count
+            .parse()
+            .map_err(|_| FenError::InvalidTurnsCount)?;
+
+        let rules = match rules {
+            Some(name) => parse_rules(name).ok_or(FenError::UnknownRules)?,
+            None => RuleSet::CLASSIC,
+        };
+
+        Ok(Self {
+            board,
+            side_to_move,
+            turns_count,
+            rules,
+        })
+    }
+}
+
+/// Rules field, `None` for the classic rules
+pub(crate) fn rules_name(rules: RuleSet) -> Option<String> {
+    let names = [
+        (rules.diagonal, "diagonal"),
+        (!rules.steps, "jumps-only"),
+        (rules.jump_over == JumpOver::Opponent, "opponent-jumps"),
+        (rules.equalising_move, "equalising"),
+    ]
+    .into_iter()
+    .filter_map(|(differs, name)| differs.then_some(name))
+    .chain(
+        StallingPolicy::VARIANTS
+            .into_iter()
+            .find(|(_, policy)| *policy == rules.stalling && *policy != RuleSet::CLASSIC.stalling)
+            .map(|(name, _)| name),
+    )
+    .collect::<Vec<_>>();
+    (!names.is_empty()).then(|| names.join("+"))
+}
+
+/// Parses rules field, `None` if some name is unknown
+pub(crate) fn parse_rules(name: &str) -> Option<RuleSet> {
+    let mut rules = RuleSet::CLASSIC;
+    for name in name.split('+') {
+        match name {
+            "classic" => {}
+            "diagonal" => rules.diagonal = true,
+            "jumps-only" => rules.steps = false,
+            "opponent-jumps" => rules.jump_over = JumpOver::Opponent,
+            "equalising" => rules.equalising_move = true,
+            _ => rules.stalling = StallingPolicy::from_name(name)?,
+        }
+    }
+    Some(rules)
+}
+
+/// Writes rows field of `board`
+pub(crate) fn write_rows(
+    f: &mut std::fmt::Formatter<'_>,
+    board: BoardPosition,
+) -> std::fmt::Result {
+    let config = board.config();
+    for y in (0..config.height).rev() {
+        let mut free = 0;
+        for x in 0..config.width {
+            let pos = Position::from_xy(x, y);
+            let piece = if board.0[PieceColor::White as usize].has_piece_at(pos) {
+                'W'
+            } else if board.0[PieceColor::Black as usize].has_piece_at(pos) {
+                'B'
+            } else {
+                free += 1;
+                continue;
+            };
+            if free > 0 {
+                write!(f, "{free}")?;
+                free = 0;
+            }
+            write!(f, "{piece}")?;
+        }
+        if free > 0 {
+            write!(f, "{free}")?;
+        }
+        if y > 0 {
+            f.write_str("/")?;
+        }
+    }
+    Ok(())
+}
+
+/// Parses rows field, board is recognised by its size and the number of pieces
+pub(crate) fn parse_rows(rows: &str) -> Result<BoardPosition, FenError> {
+    let rows = rows.split('/').collect::<Vec<_>>();
+    let height = rows.len();
+    if height > Position::MAX_HEIGHT as usize {
+        return Err(FenError::UnknownBoard);
+    }
+    let mut width = None;
+    let mut pieces = [PositionMask::EMPTY; 2];
+    for (i, row) in rows.iter().enumerate() {
+        let y = (height - 1 - i) as u8;
+        let mut x = 0usize;
+        let mut free = 0usize;
+        for c in row.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                free = free * 10 + digit as usize;
+                // no board is wider, also keeps the run from overflowing
+                if free > Position::STRIDE as usize {
+                    return Err(FenError::InvalidRow);
+                }
+                continue;
+            }
+            x += free;
+            free = 0;
+            let color = match c {
+                'W' => PieceColor::White,
+                'B' => PieceColor::Black,
+                _ => return Err(FenError::InvalidRow),
+            };
+            if x >= Position::STRIDE as usize {
+                return Err(FenError::UnknownBoard);
+            }
+            pieces[color as usize].add_position(Position::from_xy(x as u8, y));
+            x += 1;
+        }
+        x += free;
+        if *width.get_or_insert(x) != x {
+            return Err(FenError::InvalidRow);
+        }
+    }
+
+    let width = width.unwrap_or(0);
+    let config = BoardConfig::VARIANTS
+        .into_iter()
+        .find(|config| {
+            config.width as usize == width
+                && config.height as usize == height
+                && config.pieces() == pieces[PieceColor::White as usize].count()
+        })
+        .ok_or(FenError::UnknownBoard)?;
+    let board = BoardPosition(pieces, config);
+    if !board.is_valid_position() {
+        return Err(FenError::InvalidPosition);
+    }
+    Ok(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::random_positions;
+
+    #[test]
+    fn initial_position_round_trip() {
+        let fen = Fen::initial(&BoardConfig::CLASSIC, RuleSet::CLASSIC);
+        assert_eq!(fen.to_string(), "5BBB/5BBB/5BBB/8/8/WWW5/WWW5/WWW5 w 0");
+        assert_eq!(fen.to_string().parse(), Ok(fen));
+    }
+
+    #[test]
+    fn random_positions_round_trip() {
+        let rules = [
+            RuleSet::CLASSIC,
+            RuleSet::DIAGONAL,
+            RuleSet::JUMPS_ONLY,
+            RuleSet {
+                equalising_move: true,
+                ..RuleSet::OPPONENT_JUMPS
+            },
+        ];
+        for config in BoardConfig::VARIANTS {
+            for (i, board) in random_positions(config, 50).into_iter().enumerate() {
+                let fen = Fen {
+                    board,
+                    side_to_move: [PieceColor::White, PieceColor::Black][i % 2],
+                    turns_count: i * 7,
+                    rules: rules[i % rules.len()],
+                };
+                assert_eq!(fen.to_string().parse(), Ok(fen), "{fen}");
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let rows = "5BBB/5BBB/5BBB/8/8/WWW5/WWW5/WWW5";
+        for (fen, err) in [
+            (format!("{rows} w"), FenError::MissingField),
+            (format!("{rows} w 0 classic x"), FenError::TooManyFields),
+            (format!("{rows} x 0"), FenError::InvalidSide),
+            (format!("{rows} w -1"), FenError::InvalidTurnsCount),
+            (format!("{rows} w 0 hexagonal"), FenError::UnknownRules),
+            (
+                "5BBB/5BBB/5BBB/8/8/WWW5/WWW5/WWW4 w 0".to_owned(),
+                FenError::InvalidRow,
+            ),
+            (
+                "5BBB/5BBB/5BBX/8/8/WWW5/WWW5/WWW5 w 0".to_owned(),
+                FenError::InvalidRow,
+            ),
+            ("99999999999999999999W w 0".to_owned(), FenError::InvalidRow),
+            (
+                "5BBB/5BBB/5BBB/8/8/WWW5/WWW5 w 0".to_owned(),
+                FenError::UnknownBoard,
+            ),
+            (
+                "5BBB/5BBB/5BB1/8/8/WWW5/WWW5/WWW5 w 0".to_owned(),
+                FenError::InvalidPosition,
+            ),
+        ] {
+            assert_eq!(fen.parse::<Fen>(), Err(err), "{fen}");
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Game in progress: starting position, played turns with undo/redo and repetitions
+
+use crate::fen::Fen;
+use crate::{BoardConfig, BoardPosition, GameOutcome, PieceColor, RuleSet, Turn, TurnError};
+
+/// Record of the game played from some starting position
+///
+/// Undone turns are kept until a different turn is played, so they can be redone.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Game {
+    rules: RuleSet,
+    start_side: PieceColor,
+    start_turns_count: usize,
+    /// Starting position followed by positions after each of `turns`
+    positions: Vec<BoardPosition>,
+    turns: Vec<Turn>,
+    /// Number of played (not undone) turns
+    current: usize,
+}
+
+impl Game {
+    /// Game from the initial position of `config` board
+    #[must_use]
+    #[inline]
+    pub fn new(config: &'static BoardConfig, rules: RuleSet) -> Self {
+        Self::from_position(BoardPosition::initial(config), PieceColor::White, 0, rules)
+    }
+
+    /// Game started from `board` with `side_to_move` after `turns_count` turns
+    #[must_use]
+    #[inline]
+    pub fn from_position(
+        board: BoardPosition,
+        side_to_move: PieceColor,
+        turns_count: usize,
+        rules: RuleSet,
+    ) -> Self {
+        Self {
+            rules,
+            start_side: side_to_move,
+            start_turns_count: turns_count,
+            positions: vec![board],
+            turns: Vec::new(),
+            current: 0,
+        }
+    }
+
+    /// Game started from the position of `fen`
+    #[must_use]
+    #[inline]
+    pub fn from_fen(fen: Fen) -> Self {
+        Self::from_position(fen.board, fen.side_to_move, fen.turns_count, fen.rules)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    /// Position the game was started from
+    #[must_use]
+    #[inline]
+    pub fn start(&self) -> BoardPosition {
+        self.positions[0]
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn start_side(&self) -> PieceColor {
+        self.start_side
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn start_turns_count(&self) -> usize {
+        self.start_turns_count
+    }
+
+    /// Current position
+    #[must_use]
+    #[inline]
+    pub fn board(&self) -> BoardPosition {
+        self.positions[self.current]
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn side_to_move(&self) -> PieceColor {
+        if self.current & 1 == 0 {
+            self.start_side
+        } else {
+            self.start_side.opposite()
+        }
+    }
+
+    /// Plies (turns of either player) made since the initial position, including the ones
+    /// before the start
+    #[must_use]
+    #[inline]
+    pub fn turns_count(&self) -> usize {
+        self.start_turns_count + self.current
+    }
+
+    /// Current position with the side to move, turns count and rules
+    #[must_use]
+    #[inline]
+    pub fn fen(&self) -> Fen {
+        Fen {
+            board: self.board(),
+            side_to_move: self.side_to_move(),
+            turns_count: self.turns_count(),
+            rules: self.rules,
+        }
+    }
+
+    /// Played turns, without the undone ones
+    #[must_use]
+    #[inline]
+    pub fn turns(&self) -> &[Turn] {
+        &self.turns[..self.current]
+    }
+
+    /// Undone turns which can be redone, the next one first
+    #[must_use]
+    #[inline]
+    pub fn undone_turns(&self) -> &[Turn] {
+        &self.turns[self.current..]
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn last_turn(&self) -> Option<Turn> {
+        self.turns().last().copied()
+    }
+
+    /// Result of the game, `None` while it goes on
+    #[must_use]
+    #[inline]
+    pub fn winner(&self) -> Option<GameOutcome> {
+        self.board().winner_with(self.turns_count(), self.rules)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.winner().is_some()
+    }
+
+    /// Checks and makes the turn of the side to move, dropping the undone turns
+    pub fn play(&mut self, turn: Turn) -> Result<(), TurnError> {
+        if self.is_finished() {
+            return Err(TurnError::GameFinished);
+        }
+        let board = self
+            .board()
+            .try_perform_turn_with(turn, self.side_to_move(), self.rules)?;
+
+        self.turns.truncate(self.current);
+        self.positions.truncate(self.current + 1);
+        self.turns.push(turn);
+        self.positions.push(board);
+        self.current += 1;
+        Ok(())
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        self.current > 0
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        self.current < self.turns.len()
+    }
+
+    /// Takes back the last turn, `None` at the starting position
+    pub fn undo(&mut self) -> Option<Turn> {
+        if !self.can_undo() {
+            return None;
+        }
+        self.current -= 1;
+        Some(self.turns[self.current])
+    }
+
+    /// Makes the last undone turn again
+    pub fn redo(&mut self) -> Option<Turn> {
+        if !self.can_redo() {
+            return None;
+        }
+        self.current += 1;
+        Some(self.turns[self.current - 1])
+    }
+
+    /// Number of times the current position with the same side to move occurred before
+    #[must_use]
+    pub fn repetitions(&self) -> usize {
+        let board = self.board();
+        self.positions[..self.current]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .filter(|position| **position == board)
+            .count()
+    }
+
+    /// Current position occurred before with the same side to move
+    #[must_use]
+    #[inline]
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() > 0
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(&BoardConfig::CLASSIC, RuleSet::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    /// Turn between `(x, y)` squares
+    fn turn(from: (u8, u8), to: (u8, u8)) -> Turn {
+        Turn {
+            from: Position::from_xy(from.0, from.1),
+            to: Position::from_xy(to.0, to.1),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut game = Game::default();
+        let initial = game.board();
+        let turns = [
+            turn((2, 2), (2, 3)),
+            turn((5, 5), (5, 4)),
+            turn((0, 2), (0, 3)),
+        ];
+        for turn in turns {
+            game.play(turn).unwrap();
+        }
+        let played = game.clone();
+        assert_eq!(game.turns_count(), 3);
+        assert_eq!(game.side_to_move(), PieceColor::Black);
+
+        assert_eq!(game.undo(), Some(turns[2]));
+        assert_eq!(game.undo(), Some(turns[1]));
+        assert_eq!(game.turns(), &turns[..1]);
+        assert_eq!(game.undone_turns(), &turns[1..]);
+        assert_eq!(game.side_to_move(), PieceColor::Black);
+
+        assert_eq!(game.redo(), Some(turns[1]));
+        assert_eq!(game.redo(), Some(turns[2]));
+        assert_eq!(game.redo(), None);
+        assert_eq!(game, played);
+
+        while game.undo().is_some() {}
+        assert_eq!(game.board(), initial);
+        assert!(!game.can_undo());
+
+        // new turn drops the undone ones
+        game.play(turns[2]).unwrap();
+        assert!(!game.can_redo());
+        assert_eq!(game.last_turn(), Some(turns[2]));
+    }
+
+    #[test]
+    fn illegal_turn_keeps_the_game() {
+        let mut game = Game::default();
+        game.play(turn((2, 2), (2, 3))).unwrap();
+        game.undo();
+        let before = game.clone();
+        assert_eq!(game.play(turn((2, 2), (2, 5))), Err(TurnError::Unreachable));
+        assert_eq!(game.play(turn((5, 5), (5, 4))), Err(TurnError::WrongColor));
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn repetitions_count_positions_with_the_same_side() {
+        let mut game = Game::default();
+        for _ in 0..2 {
+            for (from, to) in [((2, 2), (2, 3)), ((5, 5), (5, 4))] {
+                game.play(turn(from, to)).unwrap();
+            }
+            for (from, to) in [((2, 3), (2, 2)), ((5, 4), (5, 5))] {
+                game.play(turn(from, to)).unwrap();
+            }
+        }
+        assert_eq!(game.repetitions(), 2);
+        game.undo();
+        assert_eq!(game.repetitions(), 1);
+        game.play(turn((5, 4), (4, 4))).unwrap();
+        assert!(!game.is_repetition());
+    }
+}
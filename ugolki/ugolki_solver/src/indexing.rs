@@ -0,0 +1,950 @@
+//! Indices of positions based on the combinatorial number system
+//!
+//! Set of `k` squares taken from an ordered list, with slots `c_1 < c_2 < ... < c_k` in the
+//! list, has rank `C(c_1, 1) + C(c_2, 2) + ... + C(c_k, k)`. Ranks of all such sets of `n`
+//! squares are exactly `0..C(n, k)`, see [`Combinations`]. Pieces of two colors are ranked
+//! one after another: black pieces among all squares, then white pieces among the free ones.
+//!
+//! - [`PositionIndexer`] numbers all positions of the board with the side to move, optionally
+//!   identifying symmetric ones (see [`Reduction`])
+//! - [`CampIndexer`] numbers late game positions with few pieces outside of the target camps,
+//!   split into slices by the numbers of such pieces, it's used by the
+//!   [`tablebase`](crate::tablebase). Unlike the first one it isn't dense
+
+use crate::{BoardConfig, BoardPosition, PieceColor, Position, PositionMask, Turn};
+use std::ops::Range;
+
+/// Largest set size with precomputed binomial coefficients (pieces of the largest board)
+const MAX_SET_SIZE: usize = 16;
+
+static BINOMIALS: [[u128; MAX_SET_SIZE + 1]; Position::COUNT + 1] = {
+    let mut table = [[0; MAX_SET_SIZE + 1]; Position::COUNT + 1];
+    let mut n = 0;
+    while n <= Position::COUNT {
+        table[n][0] = 1;
+        let mut k = 1;
+        while k <= n && k <= MAX_SET_SIZE {
+            table[n][k] = table[n - 1][k - 1] + table[n - 1][k];
+            k += 1;
+        }
+        n += 1;
+    }
+    table
+};
+
+/// Number of `k` element subsets of `n` elements
+#[must_use]
+#[inline]
+pub fn binomial(n: usize, k: usize) -> u128 {
+    if n <= Position::COUNT && k <= MAX_SET_SIZE {
+        return BINOMIALS[n][k];
+    }
+    if k > n {
+        return 0;
+    }
+    let k = k.min(n - k);
+    (0..k).fold(1, |acc, i| acc * (n - i) as u128 / (i + 1) as u128)
+}
+
+/// Rank of the set of slots (bit `i` for slot `i`)
+#[inline]
+fn rank_slots(slots: u128) -> u128 {
+    let mut rank = 0;
+    let mut bits = slots;
+    let mut i = 0;
+    while bits != 0 {
+        i += 1;
+        rank += binomial(bits.trailing_zeros() as usize, i);
+        bits &= bits - 1;
+    }
+    rank
+}
+
+/// Set of `size` slots with the given rank
+#[inline]
+fn unrank_slots(mut rank: u128, size: usize) -> u128 {
+    let mut slots = 0;
+    for i in (1..=size).rev() {
+        let mut c = i - 1;
+        while binomial(c + 1, i) <= rank {
+            c += 1;
+        }
+        rank -= binomial(c, i);
+        slots |= 1 << c;
+    }
+    slots
+}
+
+/// Renumbers `slots` as if `excluded` slots were removed from the list
+#[inline]
+fn compress(slots: u128, excluded: u128) -> u128 {
+    let mut compressed = 0;
+    let mut bits = slots;
+    while bits != 0 {
+        let slot = bits.trailing_zeros();
+        let before = (excluded & ((1 << slot) - 1)).count_ones();
+        compressed |= 1 << (slot - before);
+        bits &= bits - 1;
+    }
+    compressed
+}
+
+/// Inverse of [`compress`]
+#[inline]
+fn expand(compressed: u128, excluded: u128) -> u128 {
+    let mut slots = 0;
+    let mut bits = compressed;
+    let mut free = !excluded;
+    while bits != 0 {
+        if bits & 1 != 0 {
+            slots |= 1 << free.trailing_zeros();
+        }
+        bits >>= 1;
+        free &= free - 1;
+    }
+    slots
+}
+
+/// Ordered list of squares ranking its subsets of the same size
+#[derive(Debug, Clone)]
+pub struct Combinations {
+    squares: Vec<Position>,
+    /// Index of the square in `squares`, `u8::MAX` for other squares
+    slot: [u8; Position::COUNT],
+}
+
+impl Combinations {
+    /// Squares of `mask` in increasing order
+    #[must_use]
+    pub fn new(mask: PositionMask) -> Self {
+        Self::from_squares(mask.positions_iter().collect())
+    }
+
+    /// Squares in the given order, each square should be listed once
+    #[must_use]
+    pub fn from_squares(squares: Vec<Position>) -> Self {
+        let mut slot = [u8::MAX; Position::COUNT];
+        for (i, pos) in squares.iter().enumerate() {
+            assert_eq!(slot[pos.0 as usize], u8::MAX, "square is listed twice");
+            slot[pos.0 as usize] = i as u8;
+        }
+        Self { squares, slot }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn squares(&self) -> &[Position] {
+        &self.squares
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.squares.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.squares.is_empty()
+    }
+
+    /// Number of sets of `size` squares
+    #[must_use]
+    #[inline]
+    pub fn count(&self, size: usize) -> u128 {
+        binomial(self.len(), size)
+    }
+
+    /// Squares of the list in `set` as slots
+    #[inline]
+    fn slots(&self, set: PositionMask) -> u128 {
+        set.positions_iter()
+            .map(|pos| self.slot[pos.0 as usize])
+            .filter(|slot| *slot != u8::MAX)
+            .fold(0, |slots, slot| slots | 1 << slot)
+    }
+
+    #[inline]
+    fn mask(&self, slots: u128) -> PositionMask {
+        let mut mask = PositionMask::EMPTY;
+        let mut bits = slots;
+        while bits != 0 {
+            mask.add_position(self.squares[bits.trailing_zeros() as usize]);
+            bits &= bits - 1;
+        }
+        mask
+    }
+
+    /// Rank of `set` among the sets of the same size, squares not in the list are ignored
+    #[must_use]
+    #[inline]
+    pub fn rank(&self, set: PositionMask) -> u128 {
+        rank_slots(self.slots(set))
+    }
+
+    /// Set of `size` squares with the given rank
+    #[must_use]
+    #[inline]
+    pub fn unrank(&self, rank: u128, size: usize) -> PositionMask {
+        self.mask(unrank_slots(rank, size))
+    }
+
+    /// Rank of `set` among the sets of the same size of the list without `excluded` squares
+    #[must_use]
+    #[inline]
+    pub fn rank_excluding(&self, set: PositionMask, excluded: PositionMask) -> u128 {
+        rank_slots(compress(self.slots(set), self.slots(excluded)))
+    }
+
+    /// Set of `size` squares with the given rank in the list without `excluded` squares
+    #[must_use]
+    #[inline]
+    pub fn unrank_excluding(
+        &self,
+        rank: u128,
+        size: usize,
+        excluded: PositionMask,
+    ) -> PositionMask {
+        self.mask(expand(unrank_slots(rank, size), self.slots(excluded)))
+    }
+
+    /// Number of placements of `counts` black and white pieces, `0` if they don't fit
+    #[inline]
+    fn pair_count(&self, counts: [usize; 2]) -> u128 {
+        self.len()
+            .checked_sub(counts[0])
+            .map_or(0, |free| self.count(counts[0]) * binomial(free, counts[1]))
+    }
+
+    /// Rank of black and white pieces among placements with the same counts
+    #[inline]
+    fn pair_rank(&self, pieces: [PositionMask; 2], counts: [usize; 2]) -> u128 {
+        self.rank(pieces[0]) * binomial(self.len() - counts[0], counts[1])
+            + self.rank_excluding(pieces[1], pieces[0])
+    }
+
+    #[inline]
+    fn pair_unrank(&self, rank: u128, counts: [usize; 2]) -> [PositionMask; 2] {
+        let white_count = binomial(self.len() - counts[0], counts[1]);
+        let black = self.unrank(rank / white_count, counts[0]);
+        let white = self.unrank_excluding(rank % white_count, counts[1], black);
+        [black, white]
+    }
+}
+
+/// Transformation of the position keeping the rules of the game
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    /// Reflection in the diagonal through both camps (`x` and `y` swapped), only for square
+    /// boards with square camps
+    Mirror,
+    /// Board rotated by half a turn with colors swapped, the other side is to move
+    ColorSwap,
+    /// Both [`Mirror`](Self::Mirror) and [`ColorSwap`](Self::ColorSwap)
+    MirrorColorSwap,
+}
+
+impl Symmetry {
+    #[must_use]
+    #[inline]
+    pub fn mirrors(self) -> bool {
+        matches!(self, Self::Mirror | Self::MirrorColorSwap)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn swaps_colors(self) -> bool {
+        matches!(self, Self::ColorSwap | Self::MirrorColorSwap)
+    }
+
+    #[inline]
+    fn from_parts(mirror: bool, swap_colors: bool) -> Self {
+        match (mirror, swap_colors) {
+            (false, false) => Self::Identity,
+            (true, false) => Self::Mirror,
+            (false, true) => Self::ColorSwap,
+            (true, true) => Self::MirrorColorSwap,
+        }
+    }
+
+    /// Symmetry applying `self` and then `other` (all symmetries are their own inverses)
+    #[must_use]
+    #[inline]
+    pub fn then(self, other: Self) -> Self {
+        Self::from_parts(
+            self.mirrors() != other.mirrors(),
+            self.swaps_colors() != other.swaps_colors(),
+        )
+    }
+
+    /// Symmetry keeps the camps of `config` board
+    #[must_use]
+    #[inline]
+    pub fn is_valid_for(self, config: &BoardConfig) -> bool {
+        !self.mirrors()
+            || (config.width == config.height && config.camp_width == config.camp_height)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn map_position(self, config: &BoardConfig, pos: Position) -> Position {
+        let (mut x, mut y) = (pos.get_x(), pos.get_y());
+        if self.mirrors() {
+            (x, y) = (y, x);
+        }
+        if self.swaps_colors() {
+            (x, y) = (config.width - 1 - x, config.height - 1 - y);
+        }
+        Position::from_xy(x, y)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn map_turn(self, config: &BoardConfig, turn: Turn) -> Turn {
+        Turn {
+            from: self.map_position(config, turn.from),
+            to: self.map_position(config, turn.to),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn map_color(self, color: PieceColor) -> PieceColor {
+        if self.swaps_colors() {
+            color.opposite()
+        } else {
+            color
+        }
+    }
+
+    /// Transformed position, symmetry should be [valid](Self::is_valid_for) for its board
+    #[must_use]
+    pub fn apply(self, board: BoardPosition) -> BoardPosition {
+        let config = board.config();
+        let map = |pieces: PositionMask| {
+            let mut mapped = PositionMask::EMPTY;
+            for pos in pieces.positions_iter() {
+                mapped.add_position(self.map_position(config, pos));
+            }
+            mapped
+        };
+        let [black, white] = board.0.map(map);
+        let pieces = if self.swaps_colors() {
+            [white, black]
+        } else {
+            [black, white]
+        };
+        BoardPosition(pieces, config)
+    }
+}
+
+/// Symmetric positions sharing the index of [`PositionIndexer`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Reduction {
+    /// Positions with black to move are replaced by [`Symmetry::ColorSwap`] ones, so only
+    /// positions with white to move are indexed (rules with the equalising turn or
+    /// [`HomePenalty::Loss`](crate::HomePenalty::Loss) stalling penalty, which loses black
+    /// if both players have pieces left, aren't symmetric, the side to move has to be tracked
+    /// separately for them)
+    pub colors: bool,
+    /// Position and its [`Symmetry::Mirror`] get the same index
+    pub mirror: bool,
+}
+
+impl Reduction {
+    pub const NONE: Self = Self {
+        colors: false,
+        mirror: false,
+    };
+    pub const ALL: Self = Self {
+        colors: true,
+        mirror: true,
+    };
+
+    #[must_use]
+    #[inline]
+    pub fn is_valid_for(self, config: &BoardConfig) -> bool {
+        !self.mirror || Symmetry::Mirror.is_valid_for(config)
+    }
+}
+
+/// Numbers of pieces of each color on the diagonal, above and below it
+#[derive(Debug, Copy, Clone)]
+struct MirrorClass {
+    diagonal: [usize; 2],
+    upper: [usize; 2],
+    lower: [usize; 2],
+    /// Number of placements above and below the diagonal
+    span: u128,
+    offset: u128,
+}
+
+/// Squares split by the mirror diagonal
+#[derive(Debug, Clone)]
+struct MirrorLayout {
+    diagonal: Combinations,
+    upper: Combinations,
+    /// Reflections of the `upper` squares in the same order
+    lower: Combinations,
+    /// Classes in increasing order of `(diagonal, upper)` and `offset`
+    classes: Vec<MirrorClass>,
+}
+
+impl MirrorLayout {
+    fn new(config: &BoardConfig) -> Self {
+        let squares = config.squares();
+        let diagonal = squares
+            .positions_iter()
+            .filter(|pos| pos.get_x() == pos.get_y())
+            .collect();
+        let upper = squares
+            .positions_iter()
+            .filter(|pos| pos.get_x() > pos.get_y())
+            .collect::<Vec<_>>();
+        let lower = upper
+            .iter()
+            .map(|pos| Symmetry::Mirror.map_position(config, *pos))
+            .collect();
+        let diagonal = Combinations::from_squares(diagonal);
+        let upper = Combinations::from_squares(upper);
+        let lower = Combinations::from_squares(lower);
+
+        let pieces = config.pieces() as usize;
+        let mut classes = Vec::new();
+        let mut offset = 0;
+        for diagonal_black in 0..=pieces {
+            for diagonal_white in 0..=pieces {
+                for upper_black in 0..=pieces - diagonal_black {
+                    for upper_white in 0..=pieces - diagonal_white {
+                        let class_diagonal = [diagonal_black, diagonal_white];
+                        let class_upper = [upper_black, upper_white];
+                        let class_lower = [
+                            pieces - diagonal_black - upper_black,
+                            pieces - diagonal_white - upper_white,
+                        ];
+                        // mirrored class is indexed instead
+                        if class_upper < class_lower {
+                            continue;
+                        }
+                        let span = if class_upper == class_lower {
+                            let count = upper.pair_count(class_upper);
+                            count * (count + 1) / 2
+                        } else {
+                            upper.pair_count(class_upper) * lower.pair_count(class_lower)
+                        };
+                        let len = diagonal.pair_count(class_diagonal) * span;
+                        if len == 0 {
+                            continue;
+                        }
+                        classes.push(MirrorClass {
+                            diagonal: class_diagonal,
+                            upper: class_upper,
+                            lower: class_lower,
+                            span,
+                            offset,
+                        });
+                        offset += len;
+                    }
+                }
+            }
+        }
+
+        Self {
+            diagonal,
+            upper,
+            lower,
+            classes,
+        }
+    }
+
+    fn len(&self) -> u128 {
+        self.classes.last().map_or(0, |class| {
+            class.offset + self.diagonal.pair_count(class.diagonal) * class.span
+        })
+    }
+
+    /// Index of the board and whether it was mirrored to get it
+    fn index(&self, board: BoardPosition) -> (u128, bool) {
+        let part = |squares: &Combinations| {
+            let mask = self.mask(squares);
+            board.0.map(|pieces| pieces & mask)
+        };
+        let counts = |pieces: [PositionMask; 2]| pieces.map(|pieces| pieces.count() as usize);
+        let (diagonal, upper, lower) = (part(&self.diagonal), part(&self.upper), part(&self.lower));
+        let (mut upper_counts, mut lower_counts) = (counts(upper), counts(lower));
+        let mut upper_rank = self.upper.pair_rank(upper, upper_counts);
+        let mut lower_rank = self.lower.pair_rank(lower, lower_counts);
+
+        // reflection swaps the triangles keeping the order of the squares
+        let mirrored = upper_counts < lower_counts
+            || (upper_counts == lower_counts && upper_rank < lower_rank);
+        if mirrored {
+            (upper_counts, lower_counts) = (lower_counts, upper_counts);
+            (upper_rank, lower_rank) = (lower_rank, upper_rank);
+        }
+
+        let diagonal_counts = counts(diagonal);
+        let class = self.classes[self
+            .classes
+            .binary_search_by_key(&(diagonal_counts, upper_counts), |class| {
+                (class.diagonal, class.upper)
+            })
+            .expect("every placement has its class")];
+        let in_span = if upper_counts == lower_counts {
+            upper_rank * (upper_rank + 1) / 2 + lower_rank
+        } else {
+            upper_rank * self.lower.pair_count(lower_counts) + lower_rank
+        };
+        let diagonal_rank = self.diagonal.pair_rank(diagonal, diagonal_counts);
+        (
+            class.offset + diagonal_rank * class.span + in_span,
+            mirrored,
+        )
+    }
+
+    fn pieces(&self, index: u128) -> [PositionMask; 2] {
+        let class = self.classes[self.classes.partition_point(|class| class.offset <= index) - 1];
+        let index = index - class.offset;
+        let (diagonal_rank, in_span) = (index / class.span, index % class.span);
+        let (upper_rank, lower_rank) = if class.upper == class.lower {
+            // largest `upper_rank` with `upper_rank * (upper_rank + 1) / 2 <= in_span`
+            let upper_rank = ((8 * in_span + 1).isqrt() - 1) / 2;
+            (upper_rank, in_span - upper_rank * (upper_rank + 1) / 2)
+        } else {
+            let lower_count = self.lower.pair_count(class.lower);
+            (in_span / lower_count, in_span % lower_count)
+        };
+
+        let parts = [
+            self.diagonal.pair_unrank(diagonal_rank, class.diagonal),
+            self.upper.pair_unrank(upper_rank, class.upper),
+            self.lower.pair_unrank(lower_rank, class.lower),
+        ];
+        [0, 1].map(|color| {
+            parts
+                .iter()
+                .fold(PositionMask::EMPTY, |pieces, part| pieces | part[color])
+        })
+    }
+
+    #[inline]
+    fn mask(&self, squares: &Combinations) -> PositionMask {
+        squares
+            .squares()
+            .iter()
+            .fold(PositionMask::EMPTY, |mask, pos| {
+                mask | PositionMask::from_position(*pos)
+            })
+    }
+}
+
+/// Dense index of all positions of the board with the side to move
+///
+/// Black pieces are ranked among all squares, white ones among the rest. Index of the
+/// pieces is multiplied by 2 and the side to move (0 for black) is added, unless colors
+/// are [reduced](Reduction::colors).
+///
+/// Mirror reduction splits the board into the diagonal and two triangles, a position and its
+/// reflection are placed into the same class (by the numbers of pieces in these parts)
+/// and get the same index there, so the indices stay dense.
+#[derive(Debug, Clone)]
+pub struct PositionIndexer {
+    config: &'static BoardConfig,
+    reduction: Reduction,
+    squares: Combinations,
+    mirror: Option<MirrorLayout>,
+    len: u128,
+}
+
+impl PositionIndexer {
+    /// Indexer of `config` board positions, `reduction` should be
+    /// [valid](Reduction::is_valid_for) for the board
+    #[must_use]
+    pub fn new(config: &'static BoardConfig, reduction: Reduction) -> Self {
+        assert!(
+            reduction.is_valid_for(config),
+            "board {config} doesn't have mirror symmetry"
+        );
+        let squares = Combinations::new(config.squares());
+        let mirror = reduction.mirror.then(|| MirrorLayout::new(config));
+        let pieces = config.pieces() as usize;
+        let boards = match &mirror {
+            Some(mirror) => mirror.len(),
+            None => squares.pair_count([pieces, pieces]),
+        };
+        let len = if reduction.colors { boards } else { boards * 2 };
+
+        Self {
+            config,
+            reduction,
+            squares,
+            mirror,
+            len,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn config(&self) -> &'static BoardConfig {
+        self.config
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn reduction(&self) -> Reduction {
+        self.reduction
+    }
+
+    /// Number of indices
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> u128 {
+        self.len
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Index of the position and the symmetry mapping it to the [indexed one](Self::position)
+    ///
+    /// `None` for positions of other boards and [invalid](BoardPosition::is_valid_position) ones.
+    #[must_use]
+    pub fn index(
+        &self,
+        board: BoardPosition,
+        side_to_move: PieceColor,
+    ) -> Option<(u128, Symmetry)> {
+        if board.config() != self.config || !board.is_valid_position() {
+            return None;
+        }
+        let swap_colors = self.reduction.colors && side_to_move == PieceColor::Black;
+        let (board, side_to_move) = if swap_colors {
+            (Symmetry::ColorSwap.apply(board), side_to_move.opposite())
+        } else {
+            (board, side_to_move)
+        };
+
+        let pieces = self.config.pieces() as usize;
+        let (index, mirrored) = match &self.mirror {
+            Some(mirror) => mirror.index(board),
+            None => (self.squares.pair_rank(board.0, [pieces, pieces]), false),
+        };
+        let index = if self.reduction.colors {
+            index
+        } else {
+            index * 2 + side_to_move as u128
+        };
+        Some((index, Symmetry::from_parts(mirrored, swap_colors)))
+    }
+
+    /// Position with the index (white is to move if colors are reduced)
+    #[must_use]
+    pub fn position(&self, index: u128) -> Option<(BoardPosition, PieceColor)> {
+        if index >= self.len {
+            return None;
+        }
+        let (index, side_to_move) = if self.reduction.colors {
+            (index, PieceColor::White)
+        } else if index & 1 == 0 {
+            (index / 2, PieceColor::Black)
+        } else {
+            (index / 2, PieceColor::White)
+        };
+
+        let pieces = self.config.pieces() as usize;
+        let pieces = match &self.mirror {
+            Some(mirror) => mirror.pieces(index),
+            None => self.squares.pair_unrank(index, [pieces, pieces]),
+        };
+        Some((BoardPosition(pieces, self.config), side_to_move))
+    }
+}
+
+/// Pieces of single color with few of them outside of the target camp
+#[derive(Debug, Clone)]
+struct CampSide {
+    camp: PositionMask,
+    camp_squares: Combinations,
+    outside_squares: Combinations,
+}
+
+impl CampSide {
+    fn new(config: &BoardConfig, color: PieceColor) -> Self {
+        let camp = config.target_camp(color);
+        Self {
+            camp,
+            camp_squares: Combinations::new(camp),
+            outside_squares: Combinations::new(config.squares() - camp),
+        }
+    }
+
+    /// Number of placements with `outside` pieces outside of the camp (saturates at `u64::MAX`)
+    #[inline]
+    fn len(&self, outside: usize) -> u64 {
+        let len = self.camp_squares.count(outside) * self.outside_squares.count(outside);
+        len.try_into().unwrap_or(u64::MAX)
+    }
+
+    /// Number of pieces outside and the index among placements with that number
+    #[inline]
+    fn index(&self, pieces: PositionMask) -> Option<(usize, u64)> {
+        let free_camp = self.camp - pieces;
+        let outside = pieces - self.camp;
+        let count = outside.count() as usize;
+        if free_camp.count() as usize != count {
+            return None;
+        }
+        let index = self.camp_squares.rank(free_camp) * self.outside_squares.count(count)
+            + self.outside_squares.rank(outside);
+        Some((count, index as u64))
+    }
+
+    #[inline]
+    fn pieces(&self, outside: usize, index: u64) -> PositionMask {
+        let placements = self.outside_squares.count(outside) as u64;
+        let free_camp = self
+            .camp_squares
+            .unrank((index / placements) as u128, outside);
+        let outside = self
+            .outside_squares
+            .unrank((index % placements) as u128, outside);
+        (self.camp - free_camp) | outside
+    }
+}
+
+/// Index of positions where each side has at most `max_outside` pieces outside of its
+/// target camp, with the side to move
+///
+/// Positions are split into slices by the numbers of black and white pieces outside (slices
+/// go in order of the black number, then the white one). Index of the pieces of single color
+/// ranks the free squares of the camp, then the squares occupied outside; in the slice it's
+/// `(black * white_count + white) * 2 + side_to_move`. Placements of the two colors are
+/// ranked independently, so the indices of overlapping pieces are left unused
+/// ([`position`](Self::position) gives `None` for them): on the classic board with
+/// `max_outside` 1 only 352 064 of 492 032 indices are positions.
+#[derive(Debug, Clone)]
+pub struct CampIndexer {
+    config: &'static BoardConfig,
+    max_outside: usize,
+    sides: [CampSide; 2],
+    /// Start of each slice (`black * (max_outside + 1) + white`) followed by the total length
+    offsets: Vec<u64>,
+}
+
+impl CampIndexer {
+    /// Indexer of `config` board positions, `max_outside` is limited to the number of pieces
+    #[must_use]
+    pub fn new(config: &'static BoardConfig, max_outside: usize) -> Self {
+        let max_outside = max_outside.min(config.pieces() as usize);
+        let sides = [
+            CampSide::new(config, PieceColor::Black),
+            CampSide::new(config, PieceColor::White),
+        ];
+        let mut offsets = vec![0u64];
+        for black in 0..=max_outside {
+            for white in 0..=max_outside {
+                let len = sides[0]
+                    .len(black)
+                    .saturating_mul(sides[1].len(white))
+                    .saturating_mul(2);
+                offsets.push(offsets.last().unwrap().saturating_add(len));
+            }
+        }
+
+        Self {
+            config,
+            max_outside,
+            sides,
+            offsets,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn config(&self) -> &'static BoardConfig {
+        self.config
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn max_outside(&self) -> usize {
+        self.max_outside
+    }
+
+    /// Number of indices, including the unused ones (`u64::MAX` if they don't fit)
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> u64 {
+        *self.offsets.last().unwrap()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Indices of the positions with `black` and `white` pieces outside of the target camps
+    #[must_use]
+    #[inline]
+    pub fn slice(&self, black: usize, white: usize) -> Range<u64> {
+        assert!(black <= self.max_outside && white <= self.max_outside);
+        let i = black * (self.max_outside + 1) + white;
+        self.offsets[i]..self.offsets[i + 1]
+    }
+
+    /// Numbers of black and white pieces outside of the target camps, `None` if position
+    /// isn't covered
+    #[must_use]
+    pub fn slice_of(&self, board: BoardPosition) -> Option<(usize, usize)> {
+        self.side_indices(board)
+            .map(|[black, white]| (black.0, white.0))
+    }
+
+    #[inline]
+    fn side_indices(&self, board: BoardPosition) -> Option<[(usize, u64); 2]> {
+        if board.config() != self.config {
+            return None;
+        }
+        let black = self.sides[0].index(board.0[0])?;
+        let white = self.sides[1].index(board.0[1])?;
+        (black.0 <= self.max_outside && white.0 <= self.max_outside).then_some([black, white])
+    }
+
+    /// `None` if position isn't covered (including positions of other boards)
+    #[must_use]
+    #[inline]
+    pub fn index(&self, board: BoardPosition, side_to_move: PieceColor) -> Option<u64> {
+        let [(black_outside, black), (white_outside, white)] = self.side_indices(board)?;
+        let slice = self.slice(black_outside, white_outside);
+        let white_len = self.sides[1].len(white_outside);
+        Some(slice.start + (black * white_len + white) * 2 + side_to_move as u64)
+    }
+
+    /// Position by index, `None` if pieces overlap
+    #[must_use]
+    pub fn position(&self, index: u64) -> Option<(BoardPosition, PieceColor)> {
+        if index >= self.len() {
+            return None;
+        }
+        let slice = self.offsets.partition_point(|offset| *offset <= index) - 1;
+        let (black_outside, white_outside) = (
+            slice / (self.max_outside + 1),
+            slice % (self.max_outside + 1),
+        );
+        let index = index - self.offsets[slice];
+        let side_to_move = if index & 1 == 0 {
+            PieceColor::Black
+        } else {
+            PieceColor::White
+        };
+
+        let index = index / 2;
+        let white_len = self.sides[1].len(white_outside);
+        let board = BoardPosition(
+            [
+                self.sides[0].pieces(black_outside, index / white_len),
+                self.sides[1].pieces(white_outside, index % white_len),
+            ],
+            self.config,
+        );
+        board.is_valid_position().then_some((board, side_to_move))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::random_positions;
+
+    #[test]
+    fn combinations_rank_round_trip() {
+        let squares = Combinations::new(BoardConfig::SMALL.squares());
+        let excluded = squares.unrank(11, 3);
+        for size in 0..=4 {
+            for rank in 0..squares.count(size) {
+                let set = squares.unrank(rank, size);
+                assert_eq!(set.count() as usize, size);
+                assert_eq!(squares.rank(set), rank);
+            }
+            for rank in 0..binomial(squares.len() - 3, size) {
+                let set = squares.unrank_excluding(rank, size, excluded);
+                assert_eq!(set & excluded, PositionMask::EMPTY);
+                assert_eq!(squares.rank_excluding(set, excluded), rank);
+            }
+        }
+    }
+
+    #[test]
+    fn position_indexer_round_trip() {
+        let reductions = [
+            Reduction::NONE,
+            Reduction {
+                colors: true,
+                mirror: false,
+            },
+            Reduction {
+                colors: false,
+                mirror: true,
+            },
+            Reduction::ALL,
+        ];
+        for config in BoardConfig::VARIANTS {
+            for reduction in reductions {
+                if !reduction.is_valid_for(config) {
+                    continue;
+                }
+                let indexer = PositionIndexer::new(config, reduction);
+                for (i, board) in random_positions(config, 100).into_iter().enumerate() {
+                    let side_to_move = [PieceColor::White, PieceColor::Black][i % 2];
+                    let (index, symmetry) = indexer.index(board, side_to_move).unwrap();
+                    assert!(index < indexer.len());
+                    assert_eq!(
+                        indexer.position(index),
+                        Some((symmetry.apply(board), symmetry.map_color(side_to_move))),
+                        "{config} {reduction:?} {board:?}"
+                    );
+                }
+                // spread over the whole range
+                let step = indexer.len() / 97 + 1;
+                for index in (0..97).map(|i| i * step) {
+                    let (board, side_to_move) = indexer.position(index).unwrap();
+                    assert!(board.is_valid_position());
+                    assert_eq!(
+                        indexer.index(board, side_to_move).map(|(index, _)| index),
+                        Some(index),
+                        "{config} {reduction:?} {board:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn camp_indexer_round_trip() {
+        for (config, max_outside, positions) in [
+            (&BoardConfig::SMALL, 1, None),
+            (&BoardConfig::CLASSIC, 1, Some(352_064)),
+        ] {
+            let indexer = CampIndexer::new(config, max_outside);
+            let mut count = 0;
+            for index in 0..indexer.len() {
+                let Some((board, side_to_move)) = indexer.position(index) else {
+                    continue;
+                };
+                count += 1;
+                assert_eq!(indexer.index(board, side_to_move), Some(index));
+                let (black, white) = indexer.slice_of(board).unwrap();
+                assert!(indexer.slice(black, white).contains(&index));
+            }
+            if let Some(positions) = positions {
+                assert_eq!(count, positions);
+            }
+        }
+    }
+}
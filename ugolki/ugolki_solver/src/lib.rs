@@ -1,70 +1,129 @@
-/// Represents a single 8x8 board tile position
+/// Represents a single board tile position
+///
+/// Positions are stored row by row with [`STRIDE`](Self::STRIDE) tiles per row, so the same
+/// position means the same tile on every board, see [`BoardConfig`] for the actual board size.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
 pub struct Position(pub u8);
 
 impl Position {
+    /// Row length of the position encoding, the widest supported board
+    pub const STRIDE: u8 = 10;
+    /// Number of rows of the position encoding, the tallest supported board
+    pub const MAX_HEIGHT: u8 = 12;
+    /// Number of distinct positions (all of them are below this value)
+    pub const COUNT: usize = Self::STRIDE as usize * Self::MAX_HEIGHT as usize;
+
     #[must_use]
     #[inline]
     pub const fn from_xy(x: u8, y: u8) -> Self {
-        Self(x | y << 3)
+        Self(x + y * Self::STRIDE)
     }
 
     #[must_use]
     #[inline]
     pub const fn get_x(self) -> u8 {
-        self.0 & 7
+        self.0 % Self::STRIDE
     }
 
     #[must_use]
     #[inline]
     pub const fn get_y(self) -> u8 {
-        self.0 >> 3
+        self.0 / Self::STRIDE
     }
 
+    /// Position above, may be outside of the smaller boards (see [`BoardConfig::contains`])
     #[must_use]
     #[inline]
     pub const fn one_up(self) -> Option<Self> {
-        if self.0 > 7 {
-            Some(Self(self.0 - 8))
+        if self.0 >= Self::STRIDE {
+            Some(Self(self.0 - Self::STRIDE))
         } else {
             None
         }
     }
 
+    /// Position below, may be outside of the smaller boards (see [`BoardConfig::contains`])
     #[must_use]
     #[inline]
     pub const fn one_down(self) -> Option<Self> {
-        if self.0 < 56 {
-            Some(Self(self.0 + 8))
+        if (self.0 as usize) + (Self::STRIDE as usize) < Self::COUNT {
+            Some(Self(self.0 + Self::STRIDE))
         } else {
             None
         }
     }
 
+    /// Position to the left, may be outside of the smaller boards (see [`BoardConfig::contains`])
     #[must_use]
     #[inline]
     pub const fn one_left(self) -> Option<Self> {
-        if self.0 & 7 != 0 {
+        if self.get_x() != 0 {
             Some(Self(self.0 - 1))
         } else {
             None
         }
     }
 
+    /// Position to the right, may be outside of the smaller boards (see [`BoardConfig::contains`])
     #[must_use]
     #[inline]
     pub const fn one_right(self) -> Option<Self> {
-        if self.0 & 7 != 7 {
+        if self.get_x() != Self::STRIDE - 1 {
             Some(Self(self.0 + 1))
         } else {
             None
         }
     }
+
+    #[must_use]
+    #[inline]
+    pub fn one_up_left(self) -> Option<Self> {
+        self.one_up()?.one_left()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn one_up_right(self) -> Option<Self> {
+        self.one_up()?.one_right()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn one_down_left(self) -> Option<Self> {
+        self.one_down()?.one_left()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn one_down_right(self) -> Option<Self> {
+        self.one_down()?.one_right()
+    }
 }
 
+/// Steps of the orthogonal directions (up, left, down, right)
+const ORTHOGONAL_DIRECTIONS: [fn(Position) -> Option<Position>; 4] = [
+    Position::one_up,
+    Position::one_left,
+    Position::one_down,
+    Position::one_right,
+];
+
+/// Orthogonal directions followed by the diagonal ones
+const ALL_DIRECTIONS: [fn(Position) -> Option<Position>; 8] = [
+    Position::one_up,
+    Position::one_left,
+    Position::one_down,
+    Position::one_right,
+    Position::one_up_left,
+    Position::one_up_right,
+    Position::one_down_left,
+    Position::one_down_right,
+];
+
 /// Piece color
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceColor {
     Black = 0,
     White = 1,
@@ -90,14 +149,50 @@ impl PieceColor {
     }
 }
 
-/// Represents set of positions on 8x8 board
+/// Represents set of positions, bit `i` stands for `Position(i)`
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
-pub struct PositionMask(pub u64);
+pub struct PositionMask(pub u128);
 
 impl PositionMask {
-    pub const WHITE_INITIAL_POSITION: PositionMask = PositionMask(7 | 7 << 8 | 7 << 16);
+    /// White pieces of the classic 8x8 board, see [`BoardConfig::starting_camp`] for other boards
+    pub const WHITE_INITIAL_POSITION: PositionMask =
+        BoardConfig::CLASSIC.starting_camp(PieceColor::White);
+    /// Black pieces of the classic 8x8 board, see [`BoardConfig::starting_camp`] for other boards
     pub const BLACK_INITIAL_POSITION: PositionMask =
-        PositionMask((224 | 224 << 8 | 224 << 16) << 40);
+        BoardConfig::CLASSIC.starting_camp(PieceColor::Black);
+
+    pub const EMPTY: PositionMask = PositionMask(0);
+    /// All [`Position::COUNT`] positions
+    pub const FULL: PositionMask = PositionMask((1 << Position::COUNT) - 1);
+    /// Positions with `x == 0`
+    pub const LEFT_EDGE: PositionMask = Self::column(0);
+    /// Positions with `x == Position::STRIDE - 1`
+    pub const RIGHT_EDGE: PositionMask = Self::column(Position::STRIDE - 1);
+
+    /// Positions with `x` in `0..width` and `y` in `0..height`
+    #[must_use]
+    #[inline]
+    pub const fn rectangle(width: u8, height: u8) -> Self {
+        let row = (1u128 << width) - 1;
+        let mut mask = 0;
+        let mut y = 0;
+        while y < height {
+            mask |= row << (y * Position::STRIDE);
+            y += 1;
+        }
+        Self(mask)
+    }
+
+    #[inline]
+    const fn column(x: u8) -> Self {
+        Self(Self::rectangle(1, Position::MAX_HEIGHT).0 << x)
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn from_position(pos: Position) -> Self {
+        Self(1 << pos.0)
+    }
 
     #[inline]
     pub fn add_position(&mut self, pos: Position) {
@@ -115,485 +210,1121 @@ impl PositionMask {
 
     #[must_use]
     #[inline]
-    pub fn positions_iter(self) -> impl Iterator<Item = Position> {
-        (0..64)
-            .map(Position)
-            .filter(move |pos| self.has_piece_at(*pos))
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
     }
-}
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Turn {
-    pub from: Position,
-    pub to: Position,
-}
+    #[must_use]
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
 
-impl Turn {
+    /// Each position moved by [`Position::one_up`], positions leaving the board are dropped
+    #[must_use]
     #[inline]
-    pub fn value_for(self, player: PieceColor) -> i32 {
-        let dx = self.to.get_x() as i32 - self.from.get_x() as i32;
-        let dy = self.to.get_y() as i32 - self.from.get_y() as i32;
+    pub const fn shift_up(self) -> Self {
+        Self(self.0 >> Position::STRIDE)
+    }
 
-        match player {
-            PieceColor::Black => -(dx + dy),
-            PieceColor::White => dx + dy,
-        }
+    /// Each position moved by [`Position::one_down`], positions leaving the board are dropped
+    #[must_use]
+    #[inline]
+    pub const fn shift_down(self) -> Self {
+        Self(self.0 << Position::STRIDE & Self::FULL.0)
     }
-}
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum EvaluationResult {
-    Defeat { in_steps: i32 },
-    Victory { in_steps: i32 },
-    InBetween { score: i32 },
-}
+    /// Each position moved by [`Position::one_left`], positions leaving the board are dropped
+    #[must_use]
+    #[inline]
+    pub const fn shift_left(self) -> Self {
+        Self(self.0 >> 1 & !Self::RIGHT_EDGE.0)
+    }
 
-impl EvaluationResult {
+    /// Each position moved by [`Position::one_right`], positions leaving the board are dropped
     #[must_use]
     #[inline]
-    pub fn opposite(self) -> Self {
-        use EvaluationResult::*;
-        match self {
-            Defeat { in_steps } => Victory { in_steps },
-            Victory { in_steps } => Defeat { in_steps },
-            InBetween { score } => InBetween { score: -score },
-        }
+    pub const fn shift_right(self) -> Self {
+        Self(self.0 << 1 & !Self::LEFT_EDGE.0 & Self::FULL.0)
     }
 
+    /// Positions one step away in any orthogonal direction
     #[must_use]
     #[inline]
-    pub fn add_step(self) -> Self {
-        use EvaluationResult::*;
-        match self {
-            Defeat { in_steps } => Defeat {
-                in_steps: in_steps + 1,
-            },
-            Victory { in_steps } => Victory {
-                in_steps: in_steps + 1,
-            },
-            other => other,
-        }
+    pub const fn neighbours(self) -> Self {
+        Self(self.shift_up().0 | self.shift_down().0 | self.shift_left().0 | self.shift_right().0)
     }
 
+    /// Positions one step away in any diagonal direction
     #[must_use]
     #[inline]
-    pub fn is_end(self) -> bool {
-        use EvaluationResult::*;
-        matches!(self, Defeat { .. } | Victory { .. })
+    pub const fn diagonal_neighbours(self) -> Self {
+        let (up, down) = (self.shift_up(), self.shift_down());
+        Self(up.shift_left().0 | up.shift_right().0 | down.shift_left().0 | down.shift_right().0)
+    }
+
+    /// Positions in increasing order
+    #[inline]
+    pub fn positions_iter(self) -> impl Iterator<Item = Position> {
+        let mut rest = self.0;
+        std::iter::from_fn(move || {
+            if rest == 0 {
+                return None;
+            }
+            let pos = Position(rest.trailing_zeros() as u8);
+            rest &= rest - 1;
+            Some(pos)
+        })
     }
 }
 
-impl std::fmt::Display for EvaluationResult {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use EvaluationResult::*;
-        match self {
-            Defeat { in_steps } => f.write_fmt(format_args!("defeat in {in_steps} turns")),
-            Victory { in_steps } => f.write_fmt(format_args!("victory in {in_steps} turns")),
-            InBetween { score } => f.write_fmt(format_args!("{score}")),
-        }
+impl std::ops::BitAnd for PositionMask {
+    type Output = Self;
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
     }
 }
 
-use std::cmp::Ordering;
+impl std::ops::BitOr for PositionMask {
+    type Output = Self;
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
 
-impl PartialOrd for EvaluationResult {
+impl std::ops::BitXor for PositionMask {
+    type Output = Self;
     #[inline]
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        use EvaluationResult::*;
-        Some(match (self, other) {
-            (InBetween { score: score_a }, InBetween { score: score_b })
-            | (Defeat { in_steps: score_a }, Defeat { in_steps: score_b })
-            | (Victory { in_steps: score_b }, Victory { in_steps: score_a }) => {
-                if *score_a == *score_b {
-                    Ordering::Equal
-                } else if *score_a < *score_b {
-                    Ordering::Less
-                } else {
-                    Ordering::Greater
-                }
-            }
+    fn bitxor(self, rhs: Self) -> Self {
+        Self(self.0 ^ rhs.0)
+    }
+}
 
-            (Defeat { .. }, _) => Ordering::Less,
-            (Victory { .. }, _) => Ordering::Greater,
-            (_, Defeat { .. }) => Ordering::Greater,
-            (_, Victory { .. }) => Ordering::Less,
-        })
+impl std::ops::Not for PositionMask {
+    type Output = Self;
+    #[inline]
+    fn not(self) -> Self {
+        Self(!self.0)
     }
 }
 
-impl Ord for EvaluationResult {
+/// Positions of the left set missing in the right one
+impl std::ops::Sub for PositionMask {
+    type Output = Self;
     #[inline]
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap_or(Ordering::Equal)
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 & !rhs.0)
     }
 }
 
-/// All position for black & white pieces
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct BoardPosition(pub [PositionMask; 2]);
+impl std::ops::BitAndAssign for PositionMask {
+    #[inline]
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
 
-impl Default for BoardPosition {
+impl std::ops::BitOrAssign for PositionMask {
     #[inline]
-    fn default() -> Self {
-        Self([
-            PositionMask::BLACK_INITIAL_POSITION,
-            PositionMask::WHITE_INITIAL_POSITION,
-        ])
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
     }
 }
 
-impl BoardPosition {
-    #[must_use]
+impl std::ops::BitXorAssign for PositionMask {
     #[inline]
-    pub fn has_winner(self, turns_count: usize) -> bool {
-        self.winner(turns_count).is_some()
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0;
     }
+}
 
-    #[must_use]
+impl std::ops::SubAssign for PositionMask {
     #[inline]
-    pub fn winner(self, turns_count: usize) -> Option<PieceColor> {
-        // works with assumption that get 2 winners - impossible
-        if self.0[0] == PositionMask::WHITE_INITIAL_POSITION {
-            Some(PieceColor::Black)
-        } else if self.0[1] == PositionMask::BLACK_INITIAL_POSITION {
-            Some(PieceColor::White)
-        } else {
-            // prevent "dead" positions
-            if turns_count > 50 {
-                if (self.0[0].0 & PositionMask::BLACK_INITIAL_POSITION.0) != 0 {
-                    Some(PieceColor::White)
-                } else if (self.0[1].0 & PositionMask::WHITE_INITIAL_POSITION.0) != 0 {
-                    Some(PieceColor::Black)
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 &= !rhs.0;
+    }
+}
+
+/// Board dimensions and camps
+///
+/// Camps are rectangles in the opposite corners: white starts in the top left one,
+/// black in the bottom right one, and each side has to fill the camp of the other.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoardConfig {
+    pub width: u8,
+    pub height: u8,
+    pub camp_width: u8,
+    pub camp_height: u8,
+    squares: PositionMask,
+    /// Starting camp of each color
+    camps: [PositionMask; 2],
+}
+
+impl BoardConfig {
+    /// 6x6 board with 4 pieces per side
+    pub const SMALL: Self = Self::new(6, 6, 2, 2);
+    /// 8x8 board with 9 pieces per side
+    pub const CLASSIC: Self = Self::new(8, 8, 3, 3);
+    /// 8x8 board with 12 pieces per side (4 columns, 3 rows)
+    pub const CLASSIC_12: Self = Self::new(8, 8, 4, 3);
+    /// 10x10 board with 16 pieces per side
+    pub const LARGE: Self = Self::new(10, 10, 4, 4);
+
+    /// Predefined boards, see [`find`](Self::find)
+    pub const VARIANTS: [&'static Self; 4] = [
+        &Self::SMALL,
+        &Self::CLASSIC,
+        &Self::CLASSIC_12,
+        &Self::LARGE,
+    ];
+
+    /// Panics if the board doesn't fit into the position encoding, the camps don't fit
+    /// into the board or overlap
+    #[must_use]
+    pub const fn new(width: u8, height: u8, camp_width: u8, camp_height: u8) -> Self {
+        assert!(width <= Position::STRIDE && height <= Position::MAX_HEIGHT);
+        assert!(camp_width > 0 && camp_height > 0);
+        assert!(camp_width <= width && camp_height <= height);
+        assert!(2 * camp_width <= width || 2 * camp_height <= height);
+
+        let camp = PositionMask::rectangle(camp_width, camp_height);
+        let black_offset = (width - camp_width) + (height - camp_height) * Position::STRIDE;
+        Self {
+            width,
+            height,
+            camp_width,
+            camp_height,
+            squares: PositionMask::rectangle(width, height),
+            camps: [PositionMask(camp.0 << black_offset), camp],
         }
     }
 
+    /// Predefined board with these dimensions
     #[must_use]
-    #[inline]
-    pub fn all_pieces_mask(self) -> PositionMask {
-        PositionMask(self.0[0].0 | self.0[1].0)
+    pub fn find(width: u8, height: u8, camp_width: u8, camp_height: u8) -> Option<&'static Self> {
+        Self::VARIANTS.into_iter().find(|config| {
+            (
+                config.width,
+                config.height,
+                config.camp_width,
+                config.camp_height,
+            ) == (width, height, camp_width, camp_height)
+        })
+    }
+
+    /// Predefined board by its name (`"8x8/9"`, see [`Display`](std::fmt::Display) implementation)
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<&'static Self> {
+        Self::VARIANTS
+            .into_iter()
+            .find(|config| config.to_string() == name)
     }
 
+    /// Number of pieces of each side
     #[must_use]
     #[inline]
-    pub fn is_valid_position(self) -> bool {
-        self.0[0].0 & self.0[1].0 == 0
-            && self.0[0].0.count_ones() == 9
-            && self.0[1].0.count_ones() == 9
+    pub const fn pieces(&self) -> u32 {
+        self.camp_width as u32 * self.camp_height as u32
     }
 
+    /// All positions of the board
     #[must_use]
     #[inline]
-    pub fn is_valid_turn(self, turn: Turn, player_color: PieceColor) -> bool {
-        self.0[player_color as usize].has_piece_at(turn.from)
-            && !self.all_pieces_mask().has_piece_at(turn.to)
-            && turn.from != turn.to
-        // TODO check that player can move piece between positions
+    pub const fn squares(&self) -> PositionMask {
+        self.squares
     }
 
     #[must_use]
     #[inline]
-    pub fn perform_turn(self, turn: Turn, player_color: PieceColor) -> Self {
-        debug_assert!(self.is_valid_position() && self.is_valid_turn(turn, player_color));
-        let mut result = self;
-        result.0[player_color as usize].remove_position(turn.from);
-        result.0[player_color as usize].add_position(turn.to);
-        result
+    pub const fn contains(&self, pos: Position) -> bool {
+        (pos.0 as usize) < Position::COUNT && self.squares.has_piece_at(pos)
     }
 
+    /// Initial positions of the pieces of `color`
     #[must_use]
     #[inline]
-    pub fn generate_all_turns_for(self, player_color: PieceColor) -> impl Iterator<Item = Turn> {
-        self.0[player_color as usize]
-            .positions_iter()
-            .flat_map(move |pos| {
-                if player_color == PieceColor::Black {
-                    self.generate_turn_for_black(pos)
-                } else {
-                    self.generate_turn_for_white(pos)
-                }
-                .positions_iter()
-                .map(move |new_pos| Turn {
-                    from: pos,
-                    to: new_pos,
-                })
-            })
+    pub const fn starting_camp(&self, color: PieceColor) -> PositionMask {
+        self.camps[color as usize]
     }
 
-    // TODO make it Iter<Iter ... >> if possible?
-    /// "Display" purpose only (generates long "Jumps" as iterators over turn)
+    /// Positions where pieces of `color` should end the game
+    #[must_use]
     #[inline]
-    pub fn generate_all_turns_seqences_for_pos(
-        self,
-        player_color: PieceColor,
-        pos: Position,
-        f: &mut impl FnMut(&[Turn]),
-    ) {
-        debug_assert!(self.0[player_color as usize].has_piece_at(pos));
+    pub const fn target_camp(&self, color: PieceColor) -> PositionMask {
+        self.camps[color.opposite() as usize]
+    }
+}
 
-        let all_pieces = self.all_pieces_mask();
+impl std::fmt::Display for BoardConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}/{}", self.width, self.height, self.pieces())
+    }
+}
 
-        // steps
+/// Pieces which can be jumped over
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum JumpOver {
+    /// Any piece, including the own ones
+    Any,
+    /// Pieces of the other player only
+    Opponent,
+}
 
-        pos.one_up().map(|new_pos| {
-            if !all_pieces.has_piece_at(new_pos) {
-                f(&[Turn {
-                    from: pos,
-                    to: new_pos,
-                }]);
-            }
-        });
-        pos.one_down().map(|new_pos| {
-            if !all_pieces.has_piece_at(new_pos) {
-                f(&[Turn {
-                    from: pos,
-                    to: new_pos,
-                }]);
-            }
-        });
-        pos.one_left().map(|new_pos| {
-            if !all_pieces.has_piece_at(new_pos) {
-                f(&[Turn {
-                    from: pos,
-                    to: new_pos,
-                }]);
-            }
-        });
-        pos.one_right().map(|new_pos| {
-            if !all_pieces.has_piece_at(new_pos) {
-                f(&[Turn {
-                    from: pos,
-                    to: new_pos,
-                }]);
-            }
-        });
+/// Unit of the turns limit of the [`StallingPolicy`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TurnsUnit {
+    /// Single turn of either player
+    Plies,
+    /// Turn of white followed by the turn of black
+    Moves,
+}
 
-        let mut turn_stack = Vec::new();
-        let mut visited = PositionMask::default();
+/// Penalty for the pieces left in the starting camp after the turns limit
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HomePenalty {
+    /// Player with any piece left loses (black if both players have them)
+    Loss,
+    /// Player with more pieces left loses, equal counts aren't penalised
+    MorePiecesLoses,
+}
 
-        // jumps
-        fn search_jumps(
-            pos: Position,
-            turn_stack: &mut Vec<Turn>,
-            visited: &mut PositionMask,
-            all_pieces: PositionMask,
-            f: &mut impl FnMut(&[Turn]),
-        ) {
-            if visited.has_piece_at(pos) {
-                return;
-            }
-            visited.add_position(pos);
+/// Target camp occupied by pieces of the other player, which never left it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlockedCamp {
+    /// Camp has to be filled with own pieces, so the blocking side can only lose by the limit
+    NotFinished,
+    /// Filling all free squares of the camp finishes the game
+    Finished,
+}
 
-            pos.one_up().map(|joint_pos| {
-                joint_pos.one_up().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        turn_stack.push(Turn {
-                            from: pos,
-                            to: new_pos,
-                        });
-                        f(turn_stack);
-                        search_jumps(new_pos, turn_stack, visited, all_pieces, f);
-                        turn_stack.pop();
-                    }
-                })
-            });
-            pos.one_left().map(|joint_pos| {
-                joint_pos.one_left().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        turn_stack.push(Turn {
-                            from: pos,
-                            to: new_pos,
-                        });
-                        f(turn_stack);
-                        search_jumps(new_pos, turn_stack, visited, all_pieces, f);
-                        turn_stack.pop();
-                    }
-                })
-            });
-            pos.one_down().map(|joint_pos| {
-                joint_pos.one_down().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        turn_stack.push(Turn {
-                            from: pos,
-                            to: new_pos,
-                        });
-                        f(turn_stack);
-                        search_jumps(new_pos, turn_stack, visited, all_pieces, f);
-                        turn_stack.pop();
-                    }
-                })
-            });
-            pos.one_right().map(|joint_pos| {
-                joint_pos.one_right().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        turn_stack.push(Turn {
-                            from: pos,
-                            to: new_pos,
-                        });
-                        f(turn_stack);
-                        search_jumps(new_pos, turn_stack, visited, all_pieces, f);
-                        turn_stack.pop();
-                    }
-                })
-            });
-        }
+/// Anti-stalling rules: turns limit and what ends the game, [`StallingPolicy::CLASSIC`] by default
+///
+/// Turns counts passed around (like in [`BoardPosition::winner`]) are always in plies,
+/// `unit` only says how `limit` is measured.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StallingPolicy {
+    /// Pieces left in the starting camp are penalised once more than `limit` turns were made,
+    /// `None` to never penalise them
+    pub limit: Option<u16>,
+    pub unit: TurnsUnit,
+    pub penalty: HomePenalty,
+    pub blocked_camp: BlockedCamp,
+}
 
-        search_jumps(pos, &mut turn_stack, &mut visited, all_pieces, f);
+impl StallingPolicy {
+    /// Any piece left home after 50 plies loses, camp has to be filled with own pieces
+    pub const CLASSIC: Self = Self {
+        limit: Some(BoardPosition::STALLING_TURNS_LIMIT as u16),
+        unit: TurnsUnit::Plies,
+        penalty: HomePenalty::Loss,
+        blocked_camp: BlockedCamp::NotFinished,
+    };
+    /// Any piece left home after 40 moves loses, blocked camp is finished by filling the rest
+    pub const OFFICIAL: Self = Self {
+        limit: Some(40),
+        unit: TurnsUnit::Moves,
+        blocked_camp: BlockedCamp::Finished,
+        ..Self::CLASSIC
+    };
+    /// No turns limit, the game goes on until a camp is filled
+    pub const UNLIMITED: Self = Self {
+        limit: None,
+        ..Self::CLASSIC
+    };
+
+    /// Predefined policies with their names
+    pub const VARIANTS: [(&'static str, Self); 3] = [
+        ("classic", Self::CLASSIC),
+        ("official", Self::OFFICIAL),
+        ("unlimited", Self::UNLIMITED),
+    ];
+
+    /// Predefined policy by name, see [`VARIANTS`](Self::VARIANTS)
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS
+            .into_iter()
+            .find_map(|(known, policy)| (known == name).then_some(policy))
     }
 
+    /// Turns limit in plies
+    #[must_use]
     #[inline]
-    fn generate_turn_for_black(self, from: Position) -> PositionMask {
-        let all_pieces = self.all_pieces_mask();
-        let mut possible_turns = PositionMask::default();
-        let mut visited = PositionMask::default();
-
-        // regular moves
-
-        if let Some(pos) = from.one_up() {
-            possible_turns.add_position(pos)
+    pub const fn limit_plies(self) -> Option<usize> {
+        match (self.limit, self.unit) {
+            (None, _) => None,
+            (Some(limit), TurnsUnit::Plies) => Some(limit as usize),
+            (Some(limit), TurnsUnit::Moves) => Some(2 * limit as usize),
         }
-        if let Some(pos) = from.one_left() {
-            possible_turns.add_position(pos)
+    }
+
+    /// Pieces left home are penalised after `turns_count` plies
+    #[must_use]
+    #[inline]
+    pub const fn is_in_effect(self, turns_count: usize) -> bool {
+        match self.limit_plies() {
+            Some(limit) => turns_count > limit,
+            None => false,
         }
-        if let Some(pos) = from.one_down() {
-            possible_turns.add_position(pos)
+    }
+
+    /// Compact form for files: unit, penalty and blocked camp bits followed by the limit
+    /// (`u16::MAX` if there is none), [`CLASSIC`](Self::CLASSIC) flags are `0`
+    #[must_use]
+    #[inline]
+    pub const fn to_bytes(self) -> [u8; 3] {
+        let flags = self.unit as u8 | (self.penalty as u8) << 1 | (self.blocked_camp as u8) << 2;
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => u16::MAX,
         }
-        if let Some(pos) = from.one_right() {
-            possible_turns.add_position(pos)
+        .to_le_bytes();
+        [flags, limit[0], limit[1]]
+    }
+
+    /// Inverse of [`to_bytes`](Self::to_bytes), `None` for unknown flags
+    #[must_use]
+    #[inline]
+    pub const fn from_bytes(bytes: [u8; 3]) -> Option<Self> {
+        let flags = bytes[0];
+        if flags >> 3 != 0 {
+            return None;
         }
+        let limit = u16::from_le_bytes([bytes[1], bytes[2]]);
+        Some(Self {
+            limit: if limit == u16::MAX { None } else { Some(limit) },
+            unit: if flags & 1 == 0 {
+                TurnsUnit::Plies
+            } else {
+                TurnsUnit::Moves
+            },
+            penalty: if flags & 2 == 0 {
+                HomePenalty::Loss
+            } else {
+                HomePenalty::MorePiecesLoses
+            },
+            blocked_camp: if flags & 4 == 0 {
+                BlockedCamp::NotFinished
+            } else {
+                BlockedCamp::Finished
+            },
+        })
+    }
+}
 
-        possible_turns.0 &= !all_pieces.0;
+impl Default for StallingPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
 
-        // jumps
-        fn search_jumps(
-            pos: Position,
-            possible_turns: &mut PositionMask,
-            visited: &mut PositionMask,
-            all_pieces: PositionMask,
-        ) {
-            if visited.has_piece_at(pos) {
-                return;
+/// How pieces move and when the game ends, [`RuleSet::CLASSIC`] by default
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleSet {
+    /// Steps to a free neighbour are allowed (otherwise pieces only jump)
+    pub steps: bool,
+    /// Steps and jumps can also go diagonally
+    pub diagonal: bool,
+    pub jump_over: JumpOver,
+    pub stalling: StallingPolicy,
+    /// Black (moving second) gets one more turn after white fills the camp,
+    /// filling own camp with it draws the game
+    pub equalising_move: bool,
+}
+
+impl RuleSet {
+    /// Orthogonal steps and jumps over any piece
+    pub const CLASSIC: Self = Self {
+        steps: true,
+        diagonal: false,
+        jump_over: JumpOver::Any,
+        stalling: StallingPolicy::CLASSIC,
+        equalising_move: false,
+    };
+    /// Steps and jumps in all 8 directions
+    pub const DIAGONAL: Self = Self {
+        diagonal: true,
+        ..Self::CLASSIC
+    };
+    /// No steps, pieces move only by jumps
+    pub const JUMPS_ONLY: Self = Self {
+        steps: false,
+        ..Self::CLASSIC
+    };
+    /// Only pieces of the other player can be jumped over
+    pub const OPPONENT_JUMPS: Self = Self {
+        jump_over: JumpOver::Opponent,
+        ..Self::CLASSIC
+    };
+
+    /// Predefined move rules with their names, all with the classic stalling policy
+    /// and without the equalising turn
+    pub const VARIANTS: [(&'static str, Self); 4] = [
+        ("classic", Self::CLASSIC),
+        ("diagonal", Self::DIAGONAL),
+        ("jumps-only", Self::JUMPS_ONLY),
+        ("opponent-jumps", Self::OPPONENT_JUMPS),
+    ];
+
+    /// Predefined rules by name, see [`VARIANTS`](Self::VARIANTS)
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::VARIANTS
+            .into_iter()
+            .find_map(|(known, rules)| (known == name).then_some(rules))
+    }
+
+    /// Compact form of the move and equalising turn rules for files,
+    /// [`CLASSIC`](Self::CLASSIC) rules are `0`
+    ///
+    /// Stalling policy isn't included, see [`StallingPolicy::to_bytes`].
+    #[must_use]
+    #[inline]
+    pub const fn to_bits(self) -> u8 {
+        !self.steps as u8
+            | (self.diagonal as u8) << 1
+            | (self.jump_over as u8) << 2
+            | (self.equalising_move as u8) << 3
+    }
+
+    /// Inverse of [`to_bits`](Self::to_bits) with the classic stalling policy,
+    /// `None` for unknown bits
+    #[must_use]
+    #[inline]
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        if bits >> 4 != 0 {
+            return None;
+        }
+        Some(Self {
+            steps: bits & 1 == 0,
+            diagonal: bits & 2 != 0,
+            jump_over: if bits & 4 == 0 {
+                JumpOver::Any
+            } else {
+                JumpOver::Opponent
+            },
+            stalling: StallingPolicy::CLASSIC,
+            equalising_move: bits & 8 != 0,
+        })
+    }
+
+    /// Directions of steps and jumps as single step functions
+    #[inline]
+    pub(crate) fn directions(self) -> &'static [fn(Position) -> Option<Position>] {
+        if self.diagonal {
+            &ALL_DIRECTIONS
+        } else {
+            &ORTHOGONAL_DIRECTIONS
+        }
+    }
+}
+
+impl Default for RuleSet {
+    #[inline]
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Turn {
+    pub from: Position,
+    pub to: Position,
+}
+
+impl Turn {
+    #[inline]
+    pub fn value_for(self, player: PieceColor) -> i32 {
+        let dx = self.to.get_x() as i32 - self.from.get_x() as i32;
+        let dy = self.to.get_y() as i32 - self.from.get_y() as i32;
+
+        match player {
+            PieceColor::Black => -(dx + dy),
+            PieceColor::White => dx + dy,
+        }
+    }
+}
+
+/// Reason the turn can't be made
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TurnError {
+    /// Position outside of the board
+    OutOfBoard,
+    /// No piece at the starting position
+    NoPiece,
+    /// Piece at the starting position belongs to the other player
+    WrongColor,
+    /// Destination isn't free (including the turn to the same position)
+    DestinationOccupied,
+    /// Destination is neither a free neighbour nor reachable by jumps
+    Unreachable,
+    /// Game already has a winner
+    GameFinished,
+}
+
+impl std::fmt::Display for TurnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::OutOfBoard => "position is outside of the board",
+            Self::NoPiece => "no piece to move",
+            Self::WrongColor => "piece belongs to the other player",
+            Self::DestinationOccupied => "destination is occupied",
+            Self::Unreachable => "destination can't be reached by a step or jumps",
+            Self::GameFinished => "game is already finished",
+        })
+    }
+}
+
+impl std::error::Error for TurnError {}
+
+/// Result of the finished game
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameOutcome {
+    Win(PieceColor),
+    Draw,
+}
+
+/// Evaluation of the position for one of the players
+///
+/// `==` compares the variants, while ordering is by value: [`Draw`](Self::Draw) and
+/// `InBetween` with zero score are different but neither is better than the other.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EvaluationResult {
+    Defeat {
+        in_steps: i32,
+    },
+    Victory {
+        in_steps: i32,
+    },
+    InBetween {
+        score: i32,
+    },
+    /// Game ended in a draw, worth the same as `InBetween` with zero score
+    Draw,
+}
+
+impl EvaluationResult {
+    /// Score of immediate victory, see [`to_score`](Self::to_score)
+    pub const MATE_SCORE: i64 = 1 << 40;
+
+    #[must_use]
+    #[inline]
+    pub fn opposite(self) -> Self {
+        use EvaluationResult::*;
+        match self {
+            Defeat { in_steps } => Victory { in_steps },
+            Victory { in_steps } => Defeat { in_steps },
+            // `i32::MIN` has no opposite, the closest one is taken
+            InBetween { score } => InBetween {
+                score: score.saturating_neg(),
+            },
+            Draw => Draw,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn add_step(self) -> Self {
+        use EvaluationResult::*;
+        match self {
+            Defeat { in_steps } => Defeat {
+                in_steps: in_steps + 1,
+            },
+            Victory { in_steps } => Victory {
+                in_steps: in_steps + 1,
+            },
+            other => other,
+        }
+    }
+
+    /// Inverse of [`add_step`](Self::add_step), used to pass search window bounds to the next position
+    #[must_use]
+    #[inline]
+    pub fn sub_step(self) -> Self {
+        use EvaluationResult::*;
+        match self {
+            Defeat { in_steps } => Defeat {
+                in_steps: in_steps - 1,
+            },
+            Victory { in_steps } => Victory {
+                in_steps: in_steps - 1,
+            },
+            other => other,
+        }
+    }
+
+    /// Integer with the same ordering as evaluation itself, negated by [`opposite`](Self::opposite)
+    ///
+    /// `InBetween` score is kept as is and draw maps to `0`. Victory in `n` steps maps to
+    /// `MATE_SCORE - n` and defeat in `n` steps maps to `n - MATE_SCORE`.
+    #[must_use]
+    #[inline]
+    pub fn to_score(self) -> i64 {
+        use EvaluationResult::*;
+        match self {
+            Defeat { in_steps } => in_steps as i64 - Self::MATE_SCORE,
+            Victory { in_steps } => Self::MATE_SCORE - in_steps as i64,
+            InBetween { score } => score as i64,
+            Draw => 0,
+        }
+    }
+
+    /// Inverse of [`to_score`](Self::to_score) (draw comes back as equal `InBetween`),
+    /// scores between `i32` range and mates are clamped
+    #[must_use]
+    #[inline]
+    pub fn from_score(score: i64) -> Self {
+        use EvaluationResult::*;
+        if score >= Self::MATE_SCORE / 2 {
+            Victory {
+                in_steps: (Self::MATE_SCORE - score) as i32,
             }
-            visited.add_position(pos);
+        } else if score <= -Self::MATE_SCORE / 2 {
+            Defeat {
+                in_steps: (score + Self::MATE_SCORE) as i32,
+            }
+        } else {
+            InBetween {
+                score: score.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+            }
+        }
+    }
 
-            pos.one_up().map(|joint_pos| {
-                joint_pos.one_up().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        possible_turns.add_position(new_pos);
-                        search_jumps(new_pos, possible_turns, visited, all_pieces);
-                    }
-                })
-            });
-            pos.one_left().map(|joint_pos| {
-                joint_pos.one_left().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        possible_turns.add_position(new_pos);
-                        search_jumps(new_pos, possible_turns, visited, all_pieces);
-                    }
-                })
-            });
-            pos.one_down().map(|joint_pos| {
-                joint_pos.one_down().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        possible_turns.add_position(new_pos);
-                        search_jumps(new_pos, possible_turns, visited, all_pieces);
-                    }
-                })
-            });
-            pos.one_right().map(|joint_pos| {
-                joint_pos.one_right().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        possible_turns.add_position(new_pos);
-                        search_jumps(new_pos, possible_turns, visited, all_pieces);
-                    }
-                })
-            });
+    #[must_use]
+    #[inline]
+    pub fn is_end(self) -> bool {
+        use EvaluationResult::*;
+        matches!(self, Defeat { .. } | Victory { .. } | Draw)
+    }
+}
+
+impl std::fmt::Display for EvaluationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use EvaluationResult::*;
+        match self {
+            Defeat { in_steps } => f.write_fmt(format_args!("defeat in {in_steps} turns")),
+            Victory { in_steps } => f.write_fmt(format_args!("victory in {in_steps} turns")),
+            InBetween { score } => f.write_fmt(format_args!("{score}")),
+            Draw => f.write_str("draw"),
         }
+    }
+}
 
-        search_jumps(from, &mut possible_turns, &mut visited, all_pieces);
+use std::cmp::Ordering;
 
-        possible_turns
+impl PartialOrd for EvaluationResult {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
-    
+}
+
+impl Ord for EvaluationResult {
     #[inline]
-    fn generate_turn_for_white(self, from: Position) -> PositionMask {
-        let all_pieces = self.all_pieces_mask();
-        let mut possible_turns = PositionMask::default();
-        let mut visited = PositionMask::default();
+    fn cmp(&self, other: &Self) -> Ordering {
+        use EvaluationResult::*;
+        match (self, other) {
+            (InBetween { score: score_a }, InBetween { score: score_b })
+            | (Defeat { in_steps: score_a }, Defeat { in_steps: score_b })
+            | (Victory { in_steps: score_b }, Victory { in_steps: score_a }) => {
+                score_a.cmp(score_b)
+            }
+            (Draw, Draw) => Ordering::Equal,
+            (Draw, InBetween { score }) => 0.cmp(score),
+            (InBetween { score }, Draw) => score.cmp(&0),
+
+            (Defeat { .. }, _) => Ordering::Less,
+            (Victory { .. }, _) => Ordering::Greater,
+            (_, Defeat { .. }) => Ordering::Greater,
+            (_, Victory { .. }) => Ordering::Less,
+        }
+    }
+}
+
+/// All position for black & white pieces on the board
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoardPosition(pub [PositionMask; 2], pub &'static BoardConfig);
+
+impl Default for BoardPosition {
+    #[inline]
+    fn default() -> Self {
+        Self::initial(&BoardConfig::CLASSIC)
+    }
+}
+
+impl BoardPosition {
+    /// After this many turns player with a piece left in the starting camp loses
+    /// under [`StallingPolicy::CLASSIC`]
+    pub const STALLING_TURNS_LIMIT: usize = 50;
+
+    /// Pieces of both sides in their starting camps
+    #[must_use]
+    #[inline]
+    pub const fn initial(config: &'static BoardConfig) -> Self {
+        Self(
+            [
+                config.starting_camp(PieceColor::Black),
+                config.starting_camp(PieceColor::White),
+            ],
+            config,
+        )
+    }
+
+    #[must_use]
+    #[inline]
+    pub const fn config(self) -> &'static BoardConfig {
+        self.1
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn has_winner(self, turns_count: usize) -> bool {
+        self.winner(turns_count).is_some()
+    }
+
+    /// [`has_winner`](Self::has_winner) under `rules`
+    #[must_use]
+    #[inline]
+    pub fn has_winner_with(self, turns_count: usize, rules: RuleSet) -> bool {
+        self.winner_with(turns_count, rules).is_some()
+    }
+
+    /// Result of the game in the position after `turns_count` plies under the classic rules,
+    /// `None` while it goes on
+    #[must_use]
+    #[inline]
+    pub fn winner(self, turns_count: usize) -> Option<GameOutcome> {
+        self.winner_with(turns_count, RuleSet::default())
+    }
+
+    /// [`winner`](Self::winner) under `rules`
+    ///
+    /// White makes the first turn, so it's black's turn after odd number of plies
+    /// (that's when black can make the equalising turn).
+    #[must_use]
+    #[inline]
+    pub fn winner_with(self, turns_count: usize, rules: RuleSet) -> Option<GameOutcome> {
+        // works with assumption that get 2 winners - impossible
+        let config = self.1;
+        let policy = rules.stalling;
+        let finished = |color: PieceColor| {
+            let target = config.target_camp(color);
+            match policy.blocked_camp {
+                BlockedCamp::NotFinished => self.0[color as usize] == target,
+                BlockedCamp::Finished => {
+                    // other player's pieces in the camp never left it
+                    let blocking = self.0[color.opposite() as usize] & target;
+                    (self.0[color as usize] | blocking) & target == target && blocking != target
+                }
+            }
+        };
+        match (finished(PieceColor::Black), finished(PieceColor::White)) {
+            (true, true) if rules.equalising_move => return Some(GameOutcome::Draw),
+            (true, _) => return Some(GameOutcome::Win(PieceColor::Black)),
+            (false, true) if rules.equalising_move && turns_count % 2 == 1 => return None,
+            (false, true) => return Some(GameOutcome::Win(PieceColor::White)),
+            (false, false) => {}
+        }
+
+        // prevent "dead" positions
+        if !policy.is_in_effect(turns_count) {
+            return None;
+        }
+        let left_home = [PieceColor::Black, PieceColor::White]
+            .map(|color| (self.0[color as usize] & config.starting_camp(color)).count());
+        let winner = match policy.penalty {
+            HomePenalty::Loss if left_home[0] > 0 => PieceColor::White,
+            HomePenalty::Loss if left_home[1] > 0 => PieceColor::Black,
+            HomePenalty::MorePiecesLoses if left_home[0] > left_home[1] => PieceColor::White,
+            HomePenalty::MorePiecesLoses if left_home[1] > left_home[0] => PieceColor::Black,
+            _ => return None,
+        };
+        Some(GameOutcome::Win(winner))
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn all_pieces_mask(self) -> PositionMask {
+        PositionMask(self.0[0].0 | self.0[1].0)
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_valid_position(self) -> bool {
+        let config = self.1;
+        self.0[0].0 & self.0[1].0 == 0
+            && (self.0[0] | self.0[1]) - config.squares() == PositionMask::EMPTY
+            && self.0[0].count() == config.pieces()
+            && self.0[1].count() == config.pieces()
+    }
 
-        // regular moves
+    #[must_use]
+    #[inline]
+    pub fn is_valid_turn(self, turn: Turn, player_color: PieceColor) -> bool {
+        self.check_turn(turn, player_color).is_ok()
+    }
 
-        if let Some(pos) = from.one_down() {
-            possible_turns.add_position(pos)
+    #[must_use]
+    #[inline]
+    pub fn is_valid_turn_with(self, turn: Turn, player_color: PieceColor, rules: RuleSet) -> bool {
+        self.check_turn_with(turn, player_color, rules).is_ok()
+    }
+
+    /// Checks that `player_color` can make the turn by a step or a chain of jumps
+    #[inline]
+    pub fn check_turn(self, turn: Turn, player_color: PieceColor) -> Result<(), TurnError> {
+        self.check_turn_with(turn, player_color, RuleSet::default())
+    }
+
+    /// [`check_turn`](Self::check_turn) under `rules`
+    #[inline]
+    pub fn check_turn_with(
+        self,
+        turn: Turn,
+        player_color: PieceColor,
+        rules: RuleSet,
+    ) -> Result<(), TurnError> {
+        if !self.1.contains(turn.from) || !self.1.contains(turn.to) {
+            return Err(TurnError::OutOfBoard);
+        }
+        if self.0[player_color.opposite() as usize].has_piece_at(turn.from) {
+            return Err(TurnError::WrongColor);
         }
-        if let Some(pos) = from.one_right() {
-            possible_turns.add_position(pos)
+        if !self.0[player_color as usize].has_piece_at(turn.from) {
+            return Err(TurnError::NoPiece);
         }
-        if let Some(pos) = from.one_up() {
-            possible_turns.add_position(pos)
+        if self.all_pieces_mask().has_piece_at(turn.to) {
+            return Err(TurnError::DestinationOccupied);
         }
-        if let Some(pos) = from.one_left() {
-            possible_turns.add_position(pos)
+        if !self
+            .generate_turns_for_pos_with(turn.from, rules)
+            .has_piece_at(turn.to)
+        {
+            return Err(TurnError::Unreachable);
+        }
+        Ok(())
+    }
+
+    /// [`perform_turn`](Self::perform_turn) for turns not known to be legal
+    #[inline]
+    pub fn try_perform_turn(self, turn: Turn, player_color: PieceColor) -> Result<Self, TurnError> {
+        self.try_perform_turn_with(turn, player_color, RuleSet::default())
+    }
+
+    /// [`try_perform_turn`](Self::try_perform_turn) under `rules`
+    #[inline]
+    pub fn try_perform_turn_with(
+        self,
+        turn: Turn,
+        player_color: PieceColor,
+        rules: RuleSet,
+    ) -> Result<Self, TurnError> {
+        self.check_turn_with(turn, player_color, rules)?;
+        Ok(self.perform_turn(turn, player_color))
+    }
+
+    /// Moves the piece, turn is expected to be legal under the rules of the game
+    #[must_use]
+    #[inline]
+    pub fn perform_turn(self, turn: Turn, player_color: PieceColor) -> Self {
+        debug_assert!(
+            self.is_valid_position()
+                && self.0[player_color as usize].has_piece_at(turn.from)
+                && (self.1.squares() - self.all_pieces_mask()).has_piece_at(turn.to)
+        );
+        let mut result = self;
+        result.0[player_color as usize].remove_position(turn.from);
+        result.0[player_color as usize].add_position(turn.to);
+        result
+    }
+
+    #[inline]
+    pub fn generate_all_turns_for(self, player_color: PieceColor) -> impl Iterator<Item = Turn> {
+        self.generate_all_turns_for_with(player_color, RuleSet::default())
+    }
+
+    /// [`generate_all_turns_for`](Self::generate_all_turns_for) under `rules`
+    #[inline]
+    pub fn generate_all_turns_for_with(
+        self,
+        player_color: PieceColor,
+        rules: RuleSet,
+    ) -> impl Iterator<Item = Turn> {
+        self.0[player_color as usize]
+            .positions_iter()
+            .flat_map(move |pos| {
+                self.generate_turns_for_pos_with(pos, rules)
+                    .positions_iter()
+                    .map(move |new_pos| Turn {
+                        from: pos,
+                        to: new_pos,
+                    })
+            })
+    }
+
+    /// "Display" purpose only (generates long "Jumps" as iterators over turn)
+    ///
+    /// Reports single path for each destination, see [`paths_for`](Self::paths_for)
+    /// and [`path_for`](Self::path_for) for paths of the known turn.
+    #[inline]
+    pub fn generate_all_turns_seqences_for_pos(
+        self,
+        player_color: PieceColor,
+        pos: Position,
+        f: &mut impl FnMut(&[Turn]),
+    ) {
+        self.generate_all_turns_seqences_for_pos_with(player_color, pos, RuleSet::default(), f);
+    }
+
+    /// [`generate_all_turns_seqences_for_pos`](Self::generate_all_turns_seqences_for_pos)
+    /// under `rules`
+    pub fn generate_all_turns_seqences_for_pos_with(
+        self,
+        player_color: PieceColor,
+        pos: Position,
+        rules: RuleSet,
+        f: &mut impl FnMut(&[Turn]),
+    ) {
+        debug_assert!(self.0[player_color as usize].has_piece_at(pos));
+
+        let free = self.1.squares() - self.all_pieces_mask();
+        let jump_over = match rules.jump_over {
+            JumpOver::Any => self.all_pieces_mask(),
+            JumpOver::Opponent => self.0[player_color.opposite() as usize],
+        };
+
+        // steps
+        if rules.steps {
+            for step in rules.directions() {
+                if let Some(new_pos) = step(pos) {
+                    if free.has_piece_at(new_pos) {
+                        f(&[Turn {
+                            from: pos,
+                            to: new_pos,
+                        }]);
+                    }
+                }
+            }
         }
 
-        possible_turns.0 &= !all_pieces.0;
+        let mut turn_stack = Vec::new();
+        let mut visited = PositionMask::default();
 
         // jumps
         fn search_jumps(
             pos: Position,
-            possible_turns: &mut PositionMask,
+            turn_stack: &mut Vec<Turn>,
             visited: &mut PositionMask,
-            all_pieces: PositionMask,
+            (jump_over, free): (PositionMask, PositionMask),
+            directions: &[fn(Position) -> Option<Position>],
+            f: &mut impl FnMut(&[Turn]),
         ) {
             if visited.has_piece_at(pos) {
                 return;
             }
             visited.add_position(pos);
 
-            pos.one_down().map(|joint_pos| {
-                joint_pos.one_down().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        possible_turns.add_position(new_pos);
-                        search_jumps(new_pos, possible_turns, visited, all_pieces);
-                    }
-                })
-            });
-            pos.one_right().map(|joint_pos| {
-                joint_pos.one_right().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        possible_turns.add_position(new_pos);
-                        search_jumps(new_pos, possible_turns, visited, all_pieces);
-                    }
-                })
-            });
-            pos.one_up().map(|joint_pos| {
-                joint_pos.one_up().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        possible_turns.add_position(new_pos);
-                        search_jumps(new_pos, possible_turns, visited, all_pieces);
-                    }
-                })
-            });
-            pos.one_left().map(|joint_pos| {
-                joint_pos.one_left().map(|new_pos| {
-                    if all_pieces.has_piece_at(joint_pos) && !all_pieces.has_piece_at(new_pos) {
-                        possible_turns.add_position(new_pos);
-                        search_jumps(new_pos, possible_turns, visited, all_pieces);
+            for step in directions {
+                if let Some(joint_pos) = step(pos) {
+                    if let Some(new_pos) = step(joint_pos) {
+                        if jump_over.has_piece_at(joint_pos) && free.has_piece_at(new_pos) {
+                            turn_stack.push(Turn {
+                                from: pos,
+                                to: new_pos,
+                            });
+                            f(turn_stack);
+                            search_jumps(
+                                new_pos,
+                                turn_stack,
+                                visited,
+                                (jump_over, free),
+                                directions,
+                                f,
+                            );
+                            turn_stack.pop();
+                        }
                     }
-                })
-            });
+                }
+            }
         }
 
-        search_jumps(from, &mut possible_turns, &mut visited, all_pieces);
+        search_jumps(
+            pos,
+            &mut turn_stack,
+            &mut visited,
+            (jump_over, free),
+            rules.directions(),
+            f,
+        );
+    }
+
+    /// All destinations of the piece at `from`: free neighbours and everything reachable
+    /// by a chain of jumps
+    ///
+    /// Jumps are found by flood fill over the whole board at once. The moving piece
+    /// stays on `from` while jumping, so it can be jumped over but not landed on.
+    #[must_use]
+    #[inline]
+    pub fn generate_turns_for_pos(self, from: Position) -> PositionMask {
+        self.generate_turns_for_pos_with(from, RuleSet::default())
+    }
+
+    /// [`generate_turns_for_pos`](Self::generate_turns_for_pos) under `rules`
+    #[must_use]
+    #[inline]
+    pub fn generate_turns_for_pos_with(self, from: Position, rules: RuleSet) -> PositionMask {
+        let occupied = self.all_pieces_mask();
+        let free = self.1.squares() - occupied;
+        let jump_over = match rules.jump_over {
+            JumpOver::Any => occupied,
+            JumpOver::Opponent if self.0[0].has_piece_at(from) => self.0[1],
+            JumpOver::Opponent => self.0[0],
+        };
+
+        let mut reached = PositionMask::from_position(from);
+        loop {
+            let mut jumps = (reached.shift_up() & jump_over).shift_up()
+                | (reached.shift_down() & jump_over).shift_down()
+                | (reached.shift_left() & jump_over).shift_left()
+                | (reached.shift_right() & jump_over).shift_right();
+            if rules.diagonal {
+                let (up, down) = (reached.shift_up(), reached.shift_down());
+                jumps |= (up.shift_left() & jump_over).shift_up().shift_left()
+                    | (up.shift_right() & jump_over).shift_up().shift_right()
+                    | (down.shift_left() & jump_over).shift_down().shift_left()
+                    | (down.shift_right() & jump_over).shift_down().shift_right();
+            }
+            jumps &= free;
+            if (jumps - reached).is_empty() {
+                break;
+            }
+            reached |= jumps;
+        }
 
-        possible_turns
+        let mut turns = reached - PositionMask::from_position(from);
+        if rules.steps {
+            let from = PositionMask::from_position(from);
+            turns |= from.neighbours() & free;
+            if rules.diagonal {
+                turns |= from.diagonal_neighbours() & free;
+            }
+        }
+        turns
     }
 
+    /// Evaluation without search using [`DefaultEvaluator`]
     #[must_use]
     #[inline]
     pub fn immediately_evaluate_for(
@@ -601,19 +1332,37 @@ impl BoardPosition {
         player_color: PieceColor,
         turns_count: usize,
     ) -> EvaluationResult {
-        if let Some(winner) = self.winner(turns_count) {
-            if winner == player_color {
+        self.immediately_evaluate_with(
+            &DefaultEvaluator,
+            RuleSet::default(),
+            player_color,
+            turns_count,
+        )
+    }
+
+    /// Evaluation without search under `rules`, `evaluator` is used if the game isn't finished
+    #[must_use]
+    #[inline]
+    pub fn immediately_evaluate_with(
+        self,
+        evaluator: &dyn Evaluator,
+        rules: RuleSet,
+        player_color: PieceColor,
+        turns_count: usize,
+    ) -> EvaluationResult {
+        match self.winner_with(turns_count, rules) {
+            Some(GameOutcome::Win(winner)) if winner == player_color => {
                 return EvaluationResult::Victory { in_steps: 0 };
-            } else {
-                return EvaluationResult::Defeat { in_steps: 0 };
             }
+            Some(GameOutcome::Win(_)) => return EvaluationResult::Defeat { in_steps: 0 },
+            Some(GameOutcome::Draw) => return EvaluationResult::Draw,
+            None => {}
         }
 
-        let my_val = self.evaluation_helper_position_value(player_color, turns_count);
-        let other_val = self.evaluation_helper_position_value(player_color.opposite(), turns_count);
-
         EvaluationResult::InBetween {
-            score: my_val - other_val,
+            score: evaluator
+                .evaluate(self, rules, player_color, turns_count)
+                .max(-i32::MAX),
         }
     }
 
@@ -624,22 +1373,36 @@ impl BoardPosition {
         player_color: PieceColor,
         max_depth: usize,
         turns_count: usize,
+    ) -> EvaluationResult {
+        self.deep_evaluate_with(player_color, RuleSet::default(), max_depth, turns_count)
+    }
+
+    /// [`deep_evaluate_for`](Self::deep_evaluate_for) under `rules`
+    #[must_use]
+    pub fn deep_evaluate_with(
+        self,
+        player_color: PieceColor,
+        rules: RuleSet,
+        max_depth: usize,
+        turns_count: usize,
     ) -> EvaluationResult {
         fn eval_helper(
             board: BoardPosition,
+            rules: RuleSet,
             col: PieceColor,
             cur_col: PieceColor,
             depth: usize,
             turns_count: usize,
         ) -> (Option<usize>, Option<usize>) {
-            match board.winner(turns_count) {
-                Some(winner) => {
+            match board.winner_with(turns_count, rules) {
+                Some(GameOutcome::Win(winner)) => {
                     return if winner == col {
                         (Some(0), None)
                     } else {
                         (None, Some(0))
                     };
                 }
+                Some(GameOutcome::Draw) => return (None, None),
                 None => {
                     if depth == 0 {
                         return (None, None);
@@ -649,15 +1412,18 @@ impl BoardPosition {
 
             let opposite_col = cur_col.opposite();
 
-            let potisions = board.generate_all_turns_for(cur_col).map(|turn| {
-                eval_helper(
-                    board.perform_turn(turn, cur_col),
-                    col,
-                    opposite_col,
-                    depth - 1,
-                    turns_count + 1,
-                )
-            });
+            let potisions = board
+                .generate_all_turns_for_with(cur_col, rules)
+                .map(|turn| {
+                    eval_helper(
+                        board.perform_turn(turn, cur_col),
+                        rules,
+                        col,
+                        opposite_col,
+                        depth - 1,
+                        turns_count + 1,
+                    )
+                });
 
             let (victory, defeat) = if col == cur_col {
                 potisions
@@ -698,8 +1464,14 @@ impl BoardPosition {
             }
         }
 
-        let (victory, defeat) =
-            eval_helper(self, player_color, player_color, max_depth, turns_count);
+        let (victory, defeat) = eval_helper(
+            self,
+            rules,
+            player_color,
+            player_color,
+            max_depth,
+            turns_count,
+        );
 
         debug_assert!(victory.is_none() || defeat.is_none());
 
@@ -712,165 +1484,727 @@ impl BoardPosition {
                 in_steps: steps as i32,
             }
         } else {
-            self.immediately_evaluate_for(player_color, turns_count)
+            self.immediately_evaluate_with(&DefaultEvaluator, rules, player_color, turns_count)
+        }
+    }
+}
+
+impl std::fmt::Display for BoardPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use std::fmt::Write;
+        for iy in 0..self.1.height {
+            for ix in 0..self.1.width {
+                let pos = Position::from_xy(ix, iy);
+                if self.0[0].has_piece_at(pos) {
+                    f.write_char('B')?;
+                } else if self.0[1].has_piece_at(pos) {
+                    f.write_char('W')?;
+                } else {
+                    f.write_char('.')?;
+                }
+            }
+            f.write_char('\n')?;
         }
+        Ok(())
     }
+}
 
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub mod book;
+pub mod eval;
+pub mod fen;
+pub mod game;
+pub mod indexing;
+pub mod notation;
+pub mod path;
+pub mod perft;
+pub mod pgn;
+pub mod selfplay;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod tablebase;
+pub mod transposition;
+pub mod tuning;
+
+use book::OpeningBook;
+use eval::{DefaultEvaluator, Evaluator};
+use tablebase::Tablebase;
+use transposition::{Bound, TranspositionTable};
+
+/// Upper limit for iterative deepening (reached only in tiny endgames)
+const MAX_ITERATIVE_DEPTH: usize = 64;
+
+/// Deadline checks between reads of the clock
+const CLOCK_CHECK_INTERVAL: u32 = 256;
+
+/// History scores are halved when any of them grows above it
+const MAX_HISTORY_SCORE: u32 = 1 << 24;
+
+/// Window containing any evaluation
+const FULL_WINDOW: (EvaluationResult, EvaluationResult) = (
+    EvaluationResult::Defeat { in_steps: 0 },
+    EvaluationResult::Victory { in_steps: 0 },
+);
+
+/// Initial half width of aspiration window in [`EvaluationResult::to_score`] units
+/// (grows 4 times after each fail)
+const ASPIRATION_WINDOW: i64 = 2000;
+/// Fails after which aspiration window is replaced by full one
+const MAX_ASPIRATION_FAILS: usize = 3;
+
+/// Outcome of the turn search
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResult {
+    /// Evaluation for the searching player
+    pub evaluation: EvaluationResult,
+    /// Line of play expected by the searcher, starts with the chosen turn
+    pub principal_variation: Vec<Turn>,
+}
+
+impl SearchResult {
+    /// Chosen turn (`None` if player has no turns at all)
+    #[must_use]
     #[inline]
-    fn evaluation_helper_position_value(self, color: PieceColor, turns_count: usize) -> i32 {
-        let starter_pieces = self.starter_square_pieces(color);
-        let dist = self.distance_to_end_point(color);
-        let (positional_w_all, positional_w_min) = self.positional_weight(color);
+    pub fn best_turn(&self) -> Option<Turn> {
+        self.principal_variation.first().copied()
+    }
+}
 
-        let positional_w = if turns_count < 30 {
-            (positional_w_all + positional_w_min) * 70
-        } else {
-            positional_w_min * 20
-        };
+/// Turn search with shared transposition table
+///
+/// With more than one thread it runs lazy SMP: helper threads search the same position
+/// (slightly shuffled) and share their findings only through the table.
+#[derive(Debug)]
+pub struct TurnSearcher {
+    table: Arc<TranspositionTable>,
+    stop: Arc<AtomicBool>,
+    main: SearchWorker,
+    helpers: Vec<SearchWorker>,
+    book: Option<Arc<OpeningBook>>,
+    /// State of the generator choosing between book turns
+    book_random: u64,
+}
 
-        if self.starter_square_pieces(color) > 0 {
-            -dist * 200
-                - starter_pieces
-                    .pow(2)
-                    .saturating_mul((turns_count.max(10) - 10) as i32 * 800)
-        } else {
-            positional_w - dist * 700 + self.turns_weight(color) * 30
+impl TurnSearcher {
+    #[must_use]
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_table_size_mb(TranspositionTable::DEFAULT_SIZE_MB)
+    }
+
+    /// Creates searcher with transposition table taking at most `size_mb` megabytes
+    #[must_use]
+    #[inline]
+    pub fn with_table_size_mb(size_mb: usize) -> Self {
+        let table = Arc::new(TranspositionTable::new(size_mb));
+        let stop = Arc::new(AtomicBool::new(false));
+        Self {
+            main: SearchWorker::new(table.clone(), stop.clone(), 0),
+            helpers: Vec::new(),
+            table,
+            stop,
+            book: None,
+            book_random: 0,
         }
     }
 
+    /// Number of search threads (1 by default)
+    #[must_use]
     #[inline]
-    fn distance_to_end_point(self, player_color: PieceColor) -> i32 {
-        let mut pos_x: [u8; 9] = [0; 9];
-        let mut pos_y: [u8; 9] = [0; 9];
+    pub fn threads(&self) -> usize {
+        self.helpers.len() + 1
+    }
 
-        for (i, pos) in self.0[player_color as usize].positions_iter().enumerate() {
-            if player_color == PieceColor::Black {
-                pos_x[i] = pos.get_x();
-                pos_y[i] = pos.get_y();
-            } else {
-                pos_x[i] = 7 - pos.get_x();
-                pos_y[i] = 7 - pos.get_y();
-            }
+    /// Sets number of search threads, search with a single one is deterministic
+    pub fn set_threads(&mut self, threads: usize) {
+        let helpers = threads.max(1) - 1;
+        self.helpers.truncate(helpers);
+        while self.helpers.len() < helpers {
+            let id = self.helpers.len() + 1;
+            let mut helper = SearchWorker::new(self.table.clone(), self.stop.clone(), id);
+            helper.tablebase = self.main.tablebase.clone();
+            helper.evaluator = self.main.evaluator.clone();
+            helper.rules = self.main.rules;
+            helper.draw_score = self.main.draw_score;
+            self.helpers.push(helper);
+        }
+    }
+
+    /// Endgame tablebase probed by all search threads (`None` by default)
+    ///
+    /// Tablebase solved under other rules than the searcher's ones is ignored.
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        for worker in std::iter::once(&mut self.main).chain(&mut self.helpers) {
+            worker.tablebase = tablebase.clone();
         }
+    }
 
-        pos_x.sort_unstable();
-        pos_y.sort_unstable();
+    /// Evaluation of the search leaves ([`DefaultEvaluator`] by default)
+    ///
+    /// Table entries computed with the previous evaluator are dropped.
+    pub fn set_evaluator(&mut self, evaluator: Arc<dyn Evaluator>) {
+        self.table.clear();
+        for worker in std::iter::once(&mut self.main).chain(&mut self.helpers) {
+            worker.evaluator = evaluator.clone();
+        }
+    }
 
-        let mut score = 0;
+    /// Movement rules of the game ([`RuleSet::CLASSIC`] by default)
+    #[must_use]
+    #[inline]
+    pub fn rules(&self) -> RuleSet {
+        self.main.rules
+    }
 
-        for (i, x) in pos_x.into_iter().enumerate() {
-            score += (x.abs_diff(i as u8 / 3) as i32 + 1).pow(3) - 1;
+    /// Sets movement rules, table entries computed with the previous rules are dropped
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        self.table.clear();
+        for worker in std::iter::once(&mut self.main).chain(&mut self.helpers) {
+            worker.rules = rules;
         }
-        for (i, y) in pos_y.into_iter().enumerate() {
-            score += (y.abs_diff(i as u8 / 3) as i32 + 1).pow(3) - 1;
+    }
+
+    /// Score of the draw for the searching player (`0` by default)
+    #[must_use]
+    #[inline]
+    pub fn draw_score(&self) -> i32 {
+        self.main.draw_score
+    }
+
+    /// Sets score of the draw, positive to steer for draws and negative to avoid them
+    ///
+    /// Drawn lines are evaluated with it instead of [`EvaluationResult::Draw`].
+    pub fn set_draw_score(&mut self, score: i32) {
+        self.table.clear();
+        for worker in std::iter::once(&mut self.main).chain(&mut self.helpers) {
+            worker.draw_score = score;
         }
+    }
 
-        score += 15 * pos_x[8] as i32;
-        score += 15 * pos_y[8] as i32;
+    /// Number of positions evaluated by the last search (by all threads)
+    #[must_use]
+    #[inline]
+    pub fn positions_searched(&self) -> usize {
+        self.main.positions_searched
+            + self
+                .helpers
+                .iter()
+                .map(|helper| helper.positions_searched)
+                .sum::<usize>()
+    }
 
-        score
+    /// Opening book played instead of searching while position is in it (`None` by default)
+    pub fn set_book(&mut self, book: Option<Arc<OpeningBook>>) {
+        self.book = book;
     }
 
+    /// Seeds the choice between weighted book turns, same seed gives the same turns
     #[inline]
-    fn positional_weight(self, player_color: PieceColor) -> (i32, i32) {
-        let mut weight = 0;
-        let mut min_weight = 1000000;
-        for pos in self.0[player_color as usize].positions_iter() {
-            let (x, y) = if player_color == PieceColor::Black {
-                (3 - pos.get_x() as i32, 3 - pos.get_y() as i32)
-            } else {
-                (pos.get_x() as i32 - 4, pos.get_y() as i32 - 4)
-            };
+    pub fn set_book_seed(&mut self, seed: u64) {
+        self.book_random = seed;
+    }
 
-            let cur_weight = (x.max(y).pow(3)) * 10 - ((x.abs_diff(y) as i32).max(3) - 3) * 100;
+    /// Forgets all positions searched before (e.g. when new game starts)
+    pub fn clear_table(&mut self) {
+        self.table.clear();
+        for worker in std::iter::once(&mut self.main).chain(&mut self.helpers) {
+            worker.history.fill(0);
+        }
+    }
 
-            weight += cur_weight;
-            min_weight = min_weight.min(cur_weight);
+    /// Book turn with neutral evaluation, if position is in the book
+    fn book_turn(
+        &mut self,
+        board: BoardPosition,
+        player_color: PieceColor,
+    ) -> Option<SearchResult> {
+        let book = self.book.as_ref()?;
+        self.book_random = transposition::splitmix64(self.book_random);
+        let turn = book.choose(board, player_color, self.book_random)?;
+        // don't trust the file blindly
+        if !board.is_valid_turn_with(turn, player_color, self.main.rules) {
+            return None;
         }
+        Some(SearchResult {
+            evaluation: EvaluationResult::InBetween { score: 0 },
+            principal_variation: vec![turn],
+        })
+    }
 
-        (weight, min_weight)
+    fn start_search(&mut self) {
+        self.table.new_search();
+        self.stop.store(false, atomic::Ordering::Relaxed);
+        for worker in std::iter::once(&mut self.main).chain(&mut self.helpers) {
+            worker.start_search();
+        }
     }
 
+    /// Searches `max_depth` turns ahead, positions from the book are answered without search
+    #[must_use]
     #[inline]
-    fn turns_weight(self, player_color: PieceColor) -> i32 {
-        self.0[player_color as usize]
-            .positions_iter()
-            .map(|from| {
-                self.generate_turn_for_black(from)
-                    .positions_iter()
-                    .map(|to| Turn { from, to }.value_for(player_color) + 2)
-                    .max()
-                    .unwrap_or(0)
-                    .max(0)
-            })
-            .sum()
+    pub fn next_turn(
+        &mut self,
+        board: BoardPosition,
+        player_color: PieceColor,
+        max_depth: usize,
+        turns_count: usize,
+    ) -> SearchResult {
+        self.start_search();
+        if let Some(result) = self.book_turn(board, player_color) {
+            return result;
+        }
+        self.search_with_helpers(board, player_color, max_depth, turns_count, |main| {
+            let result = main.next_turn_initial_impl(
+                board,
+                player_color,
+                max_depth,
+                turns_count,
+                None,
+                FULL_WINDOW,
+            );
+            (max_depth, result)
+        })
     }
 
-    #[inline]
-    fn starter_square_pieces(self, player_color: PieceColor) -> i32 {
-        let starter_mask = if player_color == PieceColor::Black {
-            PositionMask::BLACK_INITIAL_POSITION
-        } else {
-            PositionMask::WHITE_INITIAL_POSITION
-        };
+    /// Iterative deepening search limited by wall-clock `budget`
+    ///
+    /// Book turns are played right away, like in [`next_turn`](Self::next_turn).
+    ///
+    /// Deepens one ply at a time and returns the result of the last fully finished
+    /// iteration (the deepest one among all threads). Depth 1 is always searched
+    /// to the end, so the budget can be exceeded for a very short one.
+    #[must_use]
+    pub fn next_turn_timed(
+        &mut self,
+        board: BoardPosition,
+        player_color: PieceColor,
+        budget: Duration,
+        turns_count: usize,
+    ) -> SearchResult {
+        let deadline = Instant::now() + budget;
 
-        self.0[player_color as usize]
-            .positions_iter()
-            .map(|pos| starter_mask.has_piece_at(pos) as i32)
-            .sum()
+        self.start_search();
+        if let Some(result) = self.book_turn(board, player_color) {
+            return result;
+        }
+        for worker in std::iter::once(&mut self.main).chain(&mut self.helpers) {
+            worker.deadline = Some(deadline);
+        }
+        self.search_with_helpers(
+            board,
+            player_color,
+            MAX_ITERATIVE_DEPTH,
+            turns_count,
+            |main| main.iterative_deepening(board, player_color, MAX_ITERATIVE_DEPTH, turns_count),
+        )
     }
-}
 
-impl std::fmt::Display for BoardPosition {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use std::fmt::Write;
-        for iy in 0..8 {
-            for ix in 0..8 {
-                let pos = Position::from_xy(ix, iy);
-                if self.0[0].has_piece_at(pos) {
-                    f.write_char('B')?;
-                } else if self.0[1].has_piece_at(pos) {
-                    f.write_char('W')?;
-                } else {
-                    f.write_char('.')?;
+    /// Runs `main_search` while helpers deepen the same position up to `max_depth`
+    ///
+    /// Helpers are stopped once the main thread is done, the deepest finished result among
+    /// all threads is returned (the main one if depths are equal).
+    fn search_with_helpers(
+        &mut self,
+        board: BoardPosition,
+        player_color: PieceColor,
+        max_depth: usize,
+        turns_count: usize,
+        main_search: impl FnOnce(&mut SearchWorker) -> (usize, SearchResult),
+    ) -> SearchResult {
+        let Self {
+            main,
+            helpers,
+            stop,
+            ..
+        } = self;
+        if helpers.is_empty() {
+            return main_search(main).1;
+        }
+
+        std::thread::scope(|scope| {
+            let helpers = helpers
+                .iter_mut()
+                .map(|helper| {
+                    scope.spawn(move || {
+                        helper.iterative_deepening(board, player_color, max_depth, turns_count)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut best = main_search(main);
+            stop.store(true, atomic::Ordering::Relaxed);
+
+            for helper in helpers {
+                let (depth, result) = helper.join().expect("search thread panicked");
+                if depth > best.0 {
+                    best = (depth, result);
                 }
             }
-            f.write_char('\n')?;
-        }
-        Ok(())
+            best.1
+        })
     }
 }
 
-use std::collections::HashMap;
+impl Clone for TurnSearcher {
+    /// Clone gets its own copy of the table
+    fn clone(&self) -> Self {
+        let table = Arc::new((*self.table).clone());
+        let stop = Arc::new(AtomicBool::new(false));
+        let rebind = |worker: &SearchWorker| SearchWorker {
+            table: table.clone(),
+            stop: stop.clone(),
+            ..worker.clone()
+        };
+        Self {
+            main: rebind(&self.main),
+            helpers: self.helpers.iter().map(rebind).collect(),
+            table,
+            stop,
+            book: self.book.clone(),
+            book_random: self.book_random,
+        }
+    }
+}
 
+/// Search state of a single thread
 #[derive(Debug, Clone)]
-pub struct TurnSearcher {
-    cashe: HashMap<(PieceColor, BoardPosition), EvaluationResult>,
+struct SearchWorker {
+    table: Arc<TranspositionTable>,
+    /// Set when main thread finished, helpers drop their work
+    stop: Arc<AtomicBool>,
+    /// 0 for main thread
+    id: usize,
     positions_searched: usize,
+    deadline: Option<Instant>,
+    /// Deadline checks since the last read of the clock
+    clock_checks: u32,
+    aborted: bool,
+    root_turns_count: usize,
+    root_color: PieceColor,
+    /// Score of the draw for `root_color`
+    draw_score: i32,
+    /// Triangular table, `pv_table[ply]` holds best line from the node at `ply`
+    pv_table: Vec<Vec<Turn>>,
+    /// Two last turns caused beta cutoff at each ply
+    killers: Vec<[Option<Turn>; 2]>,
+    /// Cutoffs score for each `from`, `to` pair (`from * Position::COUNT + to`)
+    history: Vec<u32>,
+    tablebase: Option<Arc<Tablebase>>,
+    evaluator: Arc<dyn Evaluator>,
+    rules: RuleSet,
 }
 
-impl TurnSearcher {
-    #[must_use]
-    #[inline]
-    pub fn new() -> Self {
+impl SearchWorker {
+    fn new(table: Arc<TranspositionTable>, stop: Arc<AtomicBool>, id: usize) -> Self {
         Self {
-            cashe: HashMap::new(),
+            table,
+            stop,
+            id,
             positions_searched: 0,
+            deadline: None,
+            clock_checks: 0,
+            aborted: false,
+            root_turns_count: 0,
+            root_color: PieceColor::White,
+            draw_score: 0,
+            pv_table: Vec::new(),
+            killers: Vec::new(),
+            history: vec![0; Position::COUNT * Position::COUNT],
+            tablebase: None,
+            evaluator: Arc::new(DefaultEvaluator),
+            rules: RuleSet::default(),
+        }
+    }
+
+    fn start_search(&mut self) {
+        self.positions_searched = 0;
+        self.deadline = None;
+        self.clock_checks = 0;
+        self.aborted = false;
+        self.killers.clear();
+        // older cutoffs are less relevant for the new position
+        self.history.iter_mut().for_each(|score| *score /= 2);
+    }
+
+    /// Deepens search up to `max_depth`, returns the last finished depth with its result
+    ///
+    /// Odd helpers skip depth 2, so threads don't search the same depth at the same time.
+    fn iterative_deepening(
+        &mut self,
+        board: BoardPosition,
+        player_color: PieceColor,
+        max_depth: usize,
+        turns_count: usize,
+    ) -> (usize, SearchResult) {
+        // depth 1 is always finished to have some turn
+        let deadline = self.deadline.take();
+        let mut best = (
+            1,
+            self.next_turn_initial_impl(board, player_color, 1, turns_count, None, FULL_WINDOW),
+        );
+        self.aborted = false;
+        self.deadline = deadline;
+
+        let first_depth = if self.id % 2 == 1 { 3 } else { 2 };
+        for depth in first_depth..=max_depth {
+            // clock is always read before starting the next iteration
+            self.clock_checks = CLOCK_CHECK_INTERVAL;
+            if best.1.evaluation.is_end() || self.out_of_time() {
+                break;
+            }
+
+            let result = self.aspiration_search(board, player_color, depth, turns_count, &best.1);
+
+            if self.aborted {
+                break;
+            }
+            best = (depth, result);
+        }
+        self.deadline = None;
+        self.aborted = false;
+
+        best
+    }
+
+    /// Checks stop flag and deadline (clock is read once in [`CLOCK_CHECK_INTERVAL`] calls),
+    /// once passed all further search is cut off
+    #[inline]
+    fn out_of_time(&mut self) -> bool {
+        if self.aborted {
+            return true;
+        }
+        self.clock_checks += 1;
+        let read_clock = self.clock_checks >= CLOCK_CHECK_INTERVAL;
+        if read_clock {
+            self.clock_checks = 0;
+        }
+        self.aborted = self.stop.load(atomic::Ordering::Relaxed)
+            || (read_clock
+                && self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline));
+        self.aborted
+    }
+
+    /// Table key of the position, draw score isn't symmetric so it depends on the searching side
+    #[inline]
+    fn table_key(&self, hash: u64, turns_count: usize) -> u64 {
+        let key = hash ^ transposition::turns_count_key(turns_count);
+        if self.draw_score == 0 {
+            key
+        } else {
+            key ^ transposition::root_key(self.root_color)
+        }
+    }
+
+    /// Evaluation of finished game or search leaf after the turn of `player_color`
+    #[inline]
+    fn leaf_evaluation(
+        &self,
+        board: BoardPosition,
+        player_color: PieceColor,
+        turns_count: usize,
+    ) -> EvaluationResult {
+        match board.immediately_evaluate_with(
+            &*self.evaluator,
+            self.rules,
+            player_color,
+            turns_count,
+        ) {
+            EvaluationResult::Draw if player_color == self.root_color => {
+                EvaluationResult::InBetween {
+                    score: self.draw_score,
+                }
+            }
+            EvaluationResult::Draw => EvaluationResult::InBetween {
+                score: self.draw_score.saturating_neg(),
+            },
+            evaluation => evaluation,
+        }
+    }
+
+    /// Sets line for the node at `ply` to `turn` followed by the line of its child
+    #[inline]
+    fn update_pv(&mut self, ply: usize, turn: Turn, from_child: bool) {
+        let (parent, child) = self.pv_table.split_at_mut(ply + 1);
+        let line = &mut parent[ply];
+        line.clear();
+        line.push(turn);
+        if from_child {
+            line.extend_from_slice(&child[0]);
+        }
+    }
+
+    #[inline]
+    fn history_index(turn: Turn) -> usize {
+        turn.from.0 as usize * Position::COUNT + turn.to.0 as usize
+    }
+
+    /// Table turn first, then killers, then by history score with distance moved forward as tie breaker
+    /// (the only order of turns that never caused a cutoff)
+    fn order_turns(
+        &self,
+        turns: &mut [Turn],
+        player_color: PieceColor,
+        ply: usize,
+        tt_turn: Option<Turn>,
+    ) {
+        let [killer_a, killer_b] = self.killers[ply];
+        turns.sort_by_cached_key(|turn| {
+            let rank = if Some(*turn) == tt_turn {
+                0
+            } else if Some(*turn) == killer_a {
+                1
+            } else if Some(*turn) == killer_b {
+                2
+            } else {
+                3
+            };
+            (
+                rank,
+                std::cmp::Reverse(self.history[Self::history_index(*turn)]),
+                std::cmp::Reverse(turn.value_for(player_color)),
+            )
+        });
+    }
+
+    /// Remembers turn caused beta cutoff
+    fn store_cutoff(&mut self, turn: Turn, ply: usize, depth: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(turn) {
+            killers[1] = killers[0];
+            killers[0] = Some(turn);
+        }
+
+        let score = &mut self.history[Self::history_index(turn)];
+        *score += (depth * depth) as u32;
+        if *score > MAX_HISTORY_SCORE {
+            self.history.iter_mut().for_each(|score| *score /= 2);
+        }
+    }
+
+    /// Follows best turns stored in the table (for nodes cut off by exact table hit)
+    fn table_line(
+        &self,
+        mut board: BoardPosition,
+        mut player_color: PieceColor,
+        mut hash: u64,
+        mut turns_count: usize,
+        max_len: usize,
+    ) -> Vec<Turn> {
+        let mut line = Vec::new();
+        while line.len() < max_len && !board.has_winner_with(turns_count, self.rules) {
+            let key = self.table_key(hash, turns_count);
+            let Some(turn) = self.table.probe(key).and_then(|entry| entry.best_turn) else {
+                break;
+            };
+            // entry may belong to other position with the same key
+            if !board
+                .generate_all_turns_for_with(player_color, self.rules)
+                .any(|valid_turn| valid_turn == turn)
+            {
+                break;
+            }
+            line.push(turn);
+            board = board.perform_turn(turn, player_color);
+            hash ^= transposition::turn_key(player_color, turn);
+            player_color = player_color.opposite();
+            turns_count += 1;
+        }
+        line
+    }
+
+    /// Searches with narrow window around `previous` iteration score, widens it on fail
+    fn aspiration_search(
+        &mut self,
+        board: BoardPosition,
+        player_color: PieceColor,
+        max_depth: usize,
+        turns_count: usize,
+        previous: &SearchResult,
+    ) -> SearchResult {
+        let mut half_width = ASPIRATION_WINDOW;
+        for _ in 0..MAX_ASPIRATION_FAILS {
+            let EvaluationResult::InBetween { .. } = previous.evaluation else {
+                break;
+            };
+            let score = previous.evaluation.to_score();
+            let window = (
+                EvaluationResult::from_score(score - half_width),
+                EvaluationResult::from_score(score + half_width),
+            );
+            let result = self.next_turn_initial_impl(
+                board,
+                player_color,
+                max_depth,
+                turns_count,
+                previous.best_turn(),
+                window,
+            );
+            if self.aborted || (window.0 < result.evaluation && result.evaluation < window.1) {
+                return result;
+            }
+            half_width *= 4;
         }
+
+        self.next_turn_initial_impl(
+            board,
+            player_color,
+            max_depth,
+            turns_count,
+            previous.best_turn(),
+            FULL_WINDOW,
+        )
     }
 
-    #[must_use]
+    /// Evaluates `turn` for `player_color` within window `alpha..beta` (returns `alpha` / `beta` on fail)
+    ///
+    /// With `scout` position is checked with null window first and searched
+    /// with full one only if `turn` turned out to be better than `alpha`.
+    #[allow(clippy::too_many_arguments)]
     #[inline]
-    pub fn next_turn(
+    fn search_turn(
         &mut self,
         board: BoardPosition,
         player_color: PieceColor,
+        hash: u64,
+        turn: Turn,
         max_depth: usize,
+        alpha: EvaluationResult,
+        beta: EvaluationResult,
         turns_count: usize,
-    ) -> (EvaluationResult, Turn) {
-        self.positions_searched = 0;
-        self.cashe.clear();
-        self.next_turn_initial_impl(board, player_color, max_depth, turns_count)
+        scout: bool,
+    ) -> EvaluationResult {
+        let new_board = board.perform_turn(turn, player_color);
+        let new_hash = hash ^ transposition::turn_key(player_color, turn);
+
+        // bounds for the next position as evaluation gets step longer on the way back
+        let mut search = |alpha: EvaluationResult, beta: EvaluationResult| {
+            self.next_turn_impl(
+                new_board,
+                player_color.opposite(),
+                new_hash,
+                max_depth - 1,
+                beta.sub_step().opposite(),
+                alpha.sub_step().opposite(),
+                turns_count + 1,
+            )
+            .opposite()
+            .add_step()
+        };
+
+        // at depth 1 evaluation is always exact
+        if scout && max_depth > 2 {
+            let evaluation = search(alpha, EvaluationResult::from_score(alpha.to_score() + 1));
+            if evaluation <= alpha || evaluation >= beta {
+                return evaluation;
+            }
+        }
+        search(alpha, beta)
     }
 
     #[inline]
@@ -880,40 +2214,116 @@ impl TurnSearcher {
         player_color: PieceColor,
         max_depth: usize,
         turns_count: usize,
-    ) -> (EvaluationResult, Turn) {
-        if max_depth == 1 {
-            self.next_turn_impl_at_depht1(board, player_color, turns_count)
+        first_turn: Option<Turn>,
+        (mut alpha, beta): (EvaluationResult, EvaluationResult),
+    ) -> SearchResult {
+        self.root_turns_count = turns_count;
+        self.root_color = player_color;
+        let hash = board.zobrist_hash(player_color);
+        let key = self.table_key(hash, turns_count);
+
+        if self.pv_table.len() < max_depth + 1 {
+            self.pv_table.resize(max_depth + 1, Vec::new());
+        }
+        if self.killers.len() < max_depth + 1 {
+            self.killers.resize(max_depth + 1, [None; 2]);
+        }
+        self.pv_table[0].clear();
+
+        let evaluation = if max_depth == 1 {
+            let (evaluation, turn) =
+                self.next_turn_impl_at_depht1(board, player_color, turns_count);
+            if turn.from != turn.to {
+                self.update_pv(0, turn, false);
+            }
+            evaluation
         } else {
-            let mut alpha = EvaluationResult::Defeat { in_steps: 0 };
-            let mut ans_turn = Turn {
-                from: Position(0),
-                to: Position(0),
-            };
-            for turn in board.generate_all_turns_for(player_color) {
+            let first_turn = first_turn.or_else(|| self.table.probe(key)?.best_turn);
+
+            let mut improved = false;
+            let mut turns = board
+                .generate_all_turns_for_with(player_color, self.rules)
+                .collect::<Vec<_>>();
+            // best known turn goes first
+            if let Some(i) = turns.iter().position(|turn| Some(*turn) == first_turn) {
+                turns[..=i].rotate_right(1);
+            }
+            // helpers look at the rest in different order
+            if turns.len() > 2 {
+                let shift = self.id % (turns.len() - 1);
+                turns[1..].rotate_left(shift);
+            }
+            for (i, turn) in turns.into_iter().enumerate() {
+                if self.out_of_time() {
+                    break;
+                }
                 let new_board = board.perform_turn(turn, player_color);
-                let evaluation = if new_board.has_winner(turns_count + 1) {
-                    new_board.immediately_evaluate_for(player_color, turns_count + 1)
-                } else {
-                    self.next_turn_impl(
-                        new_board,
-                        player_color.opposite(),
-                        max_depth - 1,
-                        EvaluationResult::Defeat { in_steps: 0 },
-                        alpha.opposite(),
-                        turns_count + 1,
-                    )
-                    .opposite()
-                    .add_step()
-                };
+                let (evaluation, from_child) =
+                    if new_board.has_winner_with(turns_count + 1, self.rules) {
+                        (
+                            self.leaf_evaluation(new_board, player_color, turns_count + 1),
+                            false,
+                        )
+                    } else {
+                        let evaluation = self.search_turn(
+                            board,
+                            player_color,
+                            hash,
+                            turn,
+                            max_depth,
+                            alpha,
+                            beta,
+                            turns_count,
+                            i > 0,
+                        );
+                        (evaluation, true)
+                    };
                 if evaluation > alpha {
                     alpha = evaluation;
-                    ans_turn = turn;
+                    improved = true;
+                    self.update_pv(0, turn, from_child);
+                } else if self.pv_table[0].is_empty() {
+                    // first turn is taken even if it loses immediately
+                    self.update_pv(0, turn, from_child);
+                }
+                if alpha >= beta {
+                    alpha = beta;
+                    break;
                 }
             }
-            (alpha, ans_turn)
+            if !self.aborted {
+                let best_turn = self.pv_table[0].first().copied();
+                let bound = if alpha >= beta {
+                    Bound::Lower
+                } else if !improved {
+                    Bound::Upper
+                } else {
+                    Bound::Exact
+                };
+                self.table.store(key, alpha, best_turn, max_depth, bound);
+            }
+            alpha
+        };
+
+        SearchResult {
+            evaluation,
+            principal_variation: self.pv_table[0].clone(),
         }
     }
 
+    #[inline]
+    fn probe_tablebase(
+        &self,
+        board: BoardPosition,
+        player_color: PieceColor,
+        turns_count: usize,
+    ) -> Option<EvaluationResult> {
+        self.tablebase
+            .as_ref()
+            .filter(|tablebase| tablebase.rules() == self.rules)?
+            .probe(board, player_color, turns_count)
+    }
+
     fn next_turn_impl_at_depht1(
         &mut self,
         board: BoardPosition,
@@ -923,15 +2333,17 @@ impl TurnSearcher {
         let choose = |(a, av), (b, bv)| if a >= b { (a, av) } else { (b, bv) };
 
         board
-            .generate_all_turns_for(player_color)
+            .generate_all_turns_for_with(player_color, self.rules)
             .map(|turn| {
                 self.positions_searched += 1;
-                (
-                    board
-                        .perform_turn(turn, player_color)
-                        .immediately_evaluate_for(player_color, turns_count + 1),
-                    turn,
-                )
+                let new_board = board.perform_turn(turn, player_color);
+                let evaluation = self
+                    .probe_tablebase(new_board, player_color.opposite(), turns_count + 1)
+                    .map(|value| value.opposite().add_step())
+                    .unwrap_or_else(|| {
+                        self.leaf_evaluation(new_board, player_color, turns_count + 1)
+                    });
+                (evaluation, turn)
             })
             .reduce(choose)
             .unwrap_or((
@@ -943,70 +2355,988 @@ impl TurnSearcher {
             ))
     }
 
+    #[allow(clippy::too_many_arguments)]
     #[inline]
     fn next_turn_impl(
         &mut self,
         board: BoardPosition,
         player_color: PieceColor,
+        hash: u64,
         max_depth: usize,
         mut alpha: EvaluationResult,
         beta: EvaluationResult,
         turns_count: usize,
     ) -> EvaluationResult {
-        if let Some(ev) = self.cashe.get(&(player_color, board)) {
-            return *ev;
+        let ply = turns_count - self.root_turns_count;
+        self.pv_table[ply].clear();
+
+        if self.out_of_time() {
+            // result is thrown away with the whole iteration
+            return alpha;
+        }
+
+        if let Some(value) = self.probe_tablebase(board, player_color, turns_count) {
+            return value;
+        }
+
+        let key = self.table_key(hash, turns_count);
+        let mut tt_turn = None;
+        if let Some(entry) = self.table.probe(key) {
+            if entry.depth as usize >= max_depth {
+                match entry.bound {
+                    Bound::Exact => {
+                        let value = entry.value;
+                        self.pv_table[ply] =
+                            self.table_line(board, player_color, hash, turns_count, max_depth);
+                        return value;
+                    }
+                    Bound::Lower if entry.value >= beta => return beta,
+                    Bound::Upper if entry.value <= alpha => return alpha,
+                    _ => {}
+                }
+            }
+            tt_turn = entry.best_turn;
+        }
+
+        if max_depth == 1 {
+            let (ret, turn) = self.next_turn_impl_at_depht1(board, player_color, turns_count);
+            let best_turn = (turn.from != turn.to).then_some(turn);
+            if let Some(turn) = best_turn {
+                self.update_pv(ply, turn, false);
+            }
+            self.table.store(key, ret, best_turn, 1, Bound::Exact);
+            return ret;
+        }
+
+        let mut best_turn = None;
+        let mut turns = board
+            .generate_all_turns_for_with(player_color, self.rules)
+            .collect::<Vec<_>>();
+        if turns.is_empty() {
+            // blocked player loses, same as at depth 1
+            let value = EvaluationResult::Defeat { in_steps: 0 };
+            self.table.store(key, value, None, max_depth, Bound::Exact);
+            return value;
+        }
+        self.order_turns(&mut turns, player_color, ply, tt_turn);
+        for (i, turn) in turns.iter().enumerate() {
+            let new_board = board.perform_turn(*turn, player_color);
+            let (evaluation, from_child) = if new_board.has_winner_with(turns_count + 1, self.rules)
+            {
+                (
+                    self.leaf_evaluation(new_board, player_color, turns_count + 1),
+                    false,
+                )
+            } else {
+                let evaluation = self.search_turn(
+                    board,
+                    player_color,
+                    hash,
+                    *turn,
+                    max_depth,
+                    alpha,
+                    beta,
+                    turns_count,
+                    i > 0,
+                );
+                (evaluation, true)
+            };
+            if evaluation > alpha {
+                alpha = evaluation;
+                best_turn = Some(*turn);
+                self.update_pv(ply, *turn, from_child);
+            }
+            if alpha >= beta {
+                alpha = beta;
+                self.store_cutoff(*turn, ply, max_depth);
+                break;
+            }
         }
 
-        let choose = |a: EvaluationResult, b: EvaluationResult| if a >= b { a } else { b };
+        if self.aborted {
+            return alpha;
+        }
 
-        let ret = if max_depth == 1 {
-            self.next_turn_impl_at_depht1(board, player_color, turns_count)
-                .0
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if best_turn.is_none() {
+            Bound::Upper
         } else {
-            let mut turns = board
-                .generate_all_turns_for(player_color)
-                .collect::<Vec<_>>();
-            turns.sort_unstable_by(|a, b| {
-                a.value_for(player_color)
-                    .cmp(&b.value_for(player_color))
-                    .reverse()
-            });
-            for turn in &turns {
-                let new_board = board.perform_turn(*turn, player_color);
-                let evaluation = if new_board.has_winner(turns_count) {
-                    new_board.immediately_evaluate_for(player_color, turns_count)
+            Bound::Exact
+        };
+        self.table.store(key, alpha, best_turn, max_depth, bound);
+
+        alpha
+    }
+}
+
+impl Default for TurnSearcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Positions with pieces placed at random (xorshift with fixed seed)
+    pub(crate) fn random_positions(
+        config: &'static BoardConfig,
+        count: usize,
+    ) -> Vec<BoardPosition> {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        let squares = config.squares().positions_iter().collect::<Vec<_>>();
+        (0..count)
+            .map(|_| {
+                let mut squares = squares.clone();
+                for i in (1..squares.len()).rev() {
+                    squares.swap(i, next(i + 1));
+                }
+                let pieces = config.pieces() as usize;
+                let mut masks = [PositionMask::EMPTY; 2];
+                for (i, pos) in squares[..2 * pieces].iter().enumerate() {
+                    masks[i / pieces].add_position(*pos);
+                }
+                BoardPosition(masks, config)
+            })
+            .collect()
+    }
+
+    /// All combinations of the move rules
+    pub(crate) fn all_rules() -> Vec<RuleSet> {
+        let mut all = Vec::new();
+        for steps in [true, false] {
+            for diagonal in [false, true] {
+                for jump_over in [JumpOver::Any, JumpOver::Opponent] {
+                    all.push(RuleSet {
+                        steps,
+                        diagonal,
+                        jump_over,
+                        ..RuleSet::CLASSIC
+                    });
+                }
+            }
+        }
+        all
+    }
+
+    /// Position of `config` board with pieces at the `(x, y)` squares, no matter how many
+    pub(crate) fn position(
+        config: &'static BoardConfig,
+        black: &[(u8, u8)],
+        white: &[(u8, u8)],
+    ) -> BoardPosition {
+        let mask = |squares: &[(u8, u8)]| {
+            let mut mask = PositionMask::EMPTY;
+            for &(x, y) in squares {
+                mask.add_position(Position::from_xy(x, y));
+            }
+            mask
+        };
+        BoardPosition([mask(black), mask(white)], config)
+    }
+
+    fn turn((from_x, from_y): (u8, u8), (to_x, to_y): (u8, u8)) -> Turn {
+        Turn {
+            from: Position::from_xy(from_x, from_y),
+            to: Position::from_xy(to_x, to_y),
+        }
+    }
+
+    /// Recursive search of the jumps replaced by the flood fill
+    fn search_jumps(
+        pos: Position,
+        rules: RuleSet,
+        over: &dyn Fn(Position) -> bool,
+        free: &dyn Fn(Position) -> bool,
+        reached: &mut PositionMask,
+    ) {
+        for step in rules.directions() {
+            let Some(middle) = step(pos) else {
+                continue;
+            };
+            let Some(to) = step(middle) else {
+                continue;
+            };
+            if over(middle) && free(to) && !reached.has_piece_at(to) {
+                reached.add_position(to);
+                search_jumps(to, rules, over, free, reached);
+            }
+        }
+    }
+
+    fn recursive_turns_for_pos(
+        board: BoardPosition,
+        from: Position,
+        rules: RuleSet,
+    ) -> PositionMask {
+        let occupied = board.all_pieces_mask();
+        let opponent = if board.0[0].has_piece_at(from) {
+            board.0[1]
+        } else {
+            board.0[0]
+        };
+        let over = |pos: Position| match rules.jump_over {
+            JumpOver::Any => occupied.has_piece_at(pos),
+            JumpOver::Opponent => opponent.has_piece_at(pos),
+        };
+        let free = |pos: Position| board.config().contains(pos) && !occupied.has_piece_at(pos);
+
+        let mut reached = PositionMask::from_position(from);
+        search_jumps(from, rules, &over, &free, &mut reached);
+        reached.remove_position(from);
+        if rules.steps {
+            for step in rules.directions() {
+                if let Some(to) = step(from).filter(|to| free(*to)) {
+                    reached.add_position(to);
+                }
+            }
+        }
+        reached
+    }
+
+    #[test]
+    fn flood_fill_matches_recursive_search() {
+        for config in BoardConfig::VARIANTS {
+            for board in random_positions(config, 200) {
+                for rules in all_rules() {
+                    for from in board.all_pieces_mask().positions_iter() {
+                        assert_eq!(
+                            board.generate_turns_for_pos_with(from, rules),
+                            recursive_turns_for_pos(board, from, rules),
+                            "{config} {rules:?} {board:?} from {from:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn perft_of_the_initial_position() {
+        let initial = BoardPosition::initial(&BoardConfig::CLASSIC);
+        let count = perft::perft(initial, PieceColor::White, 4);
+        assert_eq!(count, 53824);
+    }
+
+    #[test]
+    fn score_follows_evaluation_order() {
+        use EvaluationResult::*;
+        let evaluations = [
+            Defeat { in_steps: 1 },
+            Defeat { in_steps: 7 },
+            InBetween {
+                score: i32::MIN + 1,
+            },
+            InBetween { score: -1 },
+            InBetween { score: 0 },
+            InBetween { score: 1 },
+            InBetween { score: i32::MAX },
+            Victory { in_steps: 7 },
+            Victory { in_steps: 1 },
+        ];
+        for pair in evaluations.windows(2) {
+            assert!(pair[0] < pair[1], "{pair:?}");
+            assert!(pair[0].to_score() < pair[1].to_score(), "{pair:?}");
+        }
+        for evaluation in evaluations {
+            assert_eq!(
+                EvaluationResult::from_score(evaluation.to_score()),
+                evaluation
+            );
+            assert_eq!(
+                EvaluationResult::from_score(-evaluation.to_score()),
+                evaluation.opposite()
+            );
+        }
+        // draw comes back as the equal score
+        assert_eq!(
+            EvaluationResult::from_score(Draw.to_score()),
+            InBetween { score: 0 }
+        );
+        // null windows are built from the next score
+        for pair in evaluations[3..6].windows(2) {
+            assert_eq!(
+                EvaluationResult::from_score(pair[0].to_score() + 1),
+                pair[1]
+            );
+        }
+        assert_eq!(
+            EvaluationResult::from_score(Draw.to_score() + 1),
+            InBetween { score: 1 }
+        );
+    }
+
+    #[test]
+    fn opposite_reverses_order() {
+        use EvaluationResult::*;
+        let evaluations = [
+            Defeat { in_steps: 1 },
+            Defeat { in_steps: 7 },
+            InBetween { score: -i32::MAX },
+            InBetween { score: -1 },
+            InBetween { score: 0 },
+            Draw,
+            InBetween { score: 1 },
+            InBetween { score: i32::MAX },
+            Victory { in_steps: 7 },
+            Victory { in_steps: 1 },
+        ];
+        for a in evaluations {
+            assert_eq!(a.opposite().opposite(), a, "{a:?}");
+            for b in evaluations {
+                assert_eq!(a.cmp(&b), b.opposite().cmp(&a.opposite()), "{a:?} {b:?}");
+                assert_eq!(a.cmp(&b), a.to_score().cmp(&b.to_score()), "{a:?} {b:?}");
+                if a < b {
+                    assert!(b.opposite() < a.opposite(), "{a:?} {b:?}");
+                }
+            }
+        }
+        assert_ne!(Draw, InBetween { score: 0 });
+        assert_eq!(Draw.cmp(&InBetween { score: 0 }), Ordering::Equal);
+        assert!(Draw < InBetween { score: 1 } && InBetween { score: -1 } < Draw);
+        assert_eq!(
+            InBetween { score: i32::MIN }.opposite(),
+            InBetween { score: i32::MAX }
+        );
+    }
+
+    #[derive(Debug)]
+    struct MinEvaluator;
+
+    impl Evaluator for MinEvaluator {
+        fn evaluate(&self, _: BoardPosition, _: RuleSet, _: PieceColor, _: usize) -> i32 {
+            i32::MIN
+        }
+    }
+
+    #[test]
+    fn evaluator_output_is_negatable() {
+        let board = BoardPosition::initial(&BoardConfig::CLASSIC);
+        assert_eq!(
+            board.immediately_evaluate_with(&MinEvaluator, RuleSet::CLASSIC, PieceColor::White, 0),
+            EvaluationResult::InBetween { score: -i32::MAX }
+        );
+        let mut searcher = TurnSearcher::with_table_size_mb(1);
+        searcher.set_evaluator(Arc::new(MinEvaluator));
+        searcher.set_draw_score(i32::MIN);
+        let result = searcher.next_turn(board, PieceColor::White, 3, 0);
+        assert!(result.best_turn().is_some());
+    }
+
+    #[test]
+    fn equalising_turn() {
+        let config = &BoardConfig::SMALL;
+        let equalising = RuleSet {
+            equalising_move: true,
+            ..RuleSet::CLASSIC
+        };
+        let black_home = [(0, 0), (1, 0), (0, 1), (1, 1)];
+        let white_home = [(4, 4), (5, 4), (4, 5), (5, 5)];
+        let white_finished = position(config, &[(0, 0), (1, 0), (0, 1), (2, 2)], &white_home);
+        let both_finished = position(config, &black_home, &white_home);
+        let black_finished = position(config, &black_home, &[(4, 4), (5, 4), (4, 5), (3, 3)]);
+
+        for (board, turns_count, rules, outcome) in [
+            // black still has the equalising turn
+            (white_finished, 21, equalising, None),
+            (
+                white_finished,
+                22,
+                equalising,
+                Some(GameOutcome::Win(PieceColor::White)),
+            ),
+            (
+                white_finished,
+                21,
+                RuleSet::CLASSIC,
+                Some(GameOutcome::Win(PieceColor::White)),
+            ),
+            (both_finished, 22, equalising, Some(GameOutcome::Draw)),
+            (
+                both_finished,
+                22,
+                RuleSet::CLASSIC,
+                Some(GameOutcome::Win(PieceColor::Black)),
+            ),
+            (
+                black_finished,
+                22,
+                equalising,
+                Some(GameOutcome::Win(PieceColor::Black)),
+            ),
+        ] {
+            assert!(board.is_valid_position());
+            assert_eq!(
+                board.winner_with(turns_count, rules),
+                outcome,
+                "{board:?} {turns_count} {rules:?}"
+            );
+        }
+    }
+
+    /// Board where white can finish and let black equalise, or jump into black's camp
+    const RUNWAY: BoardConfig = BoardConfig::new(6, 2, 2, 2);
+
+    #[test]
+    fn draw_score_steers_the_search() {
+        let rules = RuleSet {
+            equalising_move: true,
+            stalling: StallingPolicy::UNLIMITED,
+            ..RuleSet::CLASSIC
+        };
+        let board = position(
+            &RUNWAY,
+            &[(0, 0), (0, 1), (1, 1), (2, 0)],
+            &[(5, 0), (4, 1), (5, 1), (3, 0)],
+        );
+        let finish = turn((3, 0), (4, 0));
+        let block = turn((3, 0), (1, 0));
+        assert!(board.is_valid_turn_with(block, PieceColor::White, rules));
+
+        for (draw_score, best_turn) in [(1_000_000, finish), (-1_000_000, block)] {
+            let mut searcher = TurnSearcher::new();
+            searcher.set_rules(rules);
+            searcher.set_draw_score(draw_score);
+            let result = searcher.next_turn(board, PieceColor::White, 2, 20);
+            assert_eq!(result.best_turn(), Some(best_turn), "{draw_score}");
+            if draw_score > 0 {
+                // black equalises right after the turn
+                assert_eq!(
+                    result.evaluation,
+                    EvaluationResult::InBetween { score: draw_score }
+                );
+                assert_eq!(result.principal_variation.len(), 2);
+            } else {
+                assert!(!result.evaluation.is_end());
+                assert!(result.evaluation.to_score() > draw_score as i64);
+            }
+        }
+    }
+
+    #[test]
+    fn single_thread_search_is_repeatable() {
+        for (i, board) in random_positions(&BoardConfig::CLASSIC, 6)
+            .into_iter()
+            .enumerate()
+        {
+            let color = [PieceColor::White, PieceColor::Black][i % 2];
+            let mut searcher = TurnSearcher::with_table_size_mb(1);
+            searcher.set_threads(1);
+            let first = searcher.next_turn(board, color, 3, 10 + i);
+            searcher.clear_table();
+            let again = searcher.next_turn(board, color, 3, 10 + i);
+            let mut other = TurnSearcher::with_table_size_mb(1);
+            other.set_threads(1);
+            let fresh = other.next_turn(board, color, 3, 10 + i);
+
+            assert!(first.best_turn().is_some());
+            assert_eq!(first, again, "{board:?}");
+            assert_eq!(first, fresh, "{board:?}");
+        }
+    }
+
+    #[test]
+    fn helper_threads_keep_fixed_depth_value() {
+        for (i, board) in random_positions(&BoardConfig::CLASSIC, 4)
+            .into_iter()
+            .enumerate()
+        {
+            let color = [PieceColor::White, PieceColor::Black][i % 2];
+            let single = TurnSearcher::with_table_size_mb(1).next_turn(board, color, 3, 10 + i);
+            let mut searcher = TurnSearcher::with_table_size_mb(1);
+            searcher.set_threads(4);
+            let result = searcher.next_turn(board, color, 3, 10 + i);
+
+            let turn = result.best_turn().expect("no turn chosen");
+            assert!(board.is_valid_turn(turn, color), "{board:?} {turn:?}");
+            assert_eq!(result.evaluation, single.evaluation, "{board:?}");
+        }
+    }
+
+    /// Plain minimax with the searcher's conventions (zero draw score), no pruning and no table
+    fn minimax(
+        board: BoardPosition,
+        color: PieceColor,
+        depth: usize,
+        turns_count: usize,
+        rules: RuleSet,
+    ) -> EvaluationResult {
+        board
+            .generate_all_turns_for_with(color, rules)
+            .map(|turn| {
+                let new_board = board.perform_turn(turn, color);
+                if depth == 1 || new_board.has_winner_with(turns_count + 1, rules) {
+                    match new_board.immediately_evaluate_with(
+                        &DefaultEvaluator,
+                        rules,
+                        color,
+                        turns_count + 1,
+                    ) {
+                        EvaluationResult::Draw => EvaluationResult::InBetween { score: 0 },
+                        evaluation => evaluation,
+                    }
                 } else {
-                    self.next_turn_impl(
+                    minimax(
                         new_board,
-                        player_color.opposite(),
-                        max_depth - 1,
-                        beta.opposite(),
-                        alpha.opposite(),
+                        color.opposite(),
+                        depth - 1,
                         turns_count + 1,
+                        rules,
                     )
                     .opposite()
                     .add_step()
-                };
-                alpha = choose(alpha, evaluation);
-                if alpha >= beta {
-                    alpha = beta;
-                    break;
                 }
+            })
+            .max()
+            .unwrap_or(EvaluationResult::Defeat { in_steps: 0 })
+    }
+
+    #[test]
+    fn search_matches_minimax() {
+        let positions = random_positions(&BoardConfig::SMALL, 8)
+            .into_iter()
+            .map(|board| (board, 4))
+            .chain(
+                random_positions(&BoardConfig::CLASSIC, 3)
+                    .into_iter()
+                    .map(|board| (board, 3)),
+            );
+        for (i, (board, depth)) in positions.enumerate() {
+            let color = [PieceColor::White, PieceColor::Black][i % 2];
+            // some searches run into the stalling limit
+            let turns_count = 44 + i % 6;
+            if board.has_winner(turns_count) {
+                continue;
             }
-            alpha
+            let expected = minimax(board, color, depth, turns_count, RuleSet::CLASSIC);
+
+            let mut searcher = TurnSearcher::with_table_size_mb(1);
+            let result = searcher.next_turn(board, color, depth, turns_count);
+            assert_eq!(
+                result.evaluation, expected,
+                "{board:?} {color:?} {turns_count}"
+            );
+
+            // iterative deepening adds aspiration windows on top
+            searcher.clear_table();
+            searcher.start_search();
+            let (reached, result) =
+                searcher
+                    .main
+                    .iterative_deepening(board, color, depth, turns_count);
+            if !result.evaluation.is_end() {
+                assert_eq!(reached, depth);
+            }
+            assert_eq!(
+                result.evaluation,
+                minimax(board, color, reached, turns_count, RuleSet::CLASSIC),
+                "{board:?} {color:?} {turns_count}"
+            );
+        }
+    }
+
+    #[test]
+    fn forced_win_is_found() {
+        // white steps to (5, 3) and jumps over (5, 4) into the last square of black's camp
+        let board = position(
+            &BoardConfig::SMALL,
+            &[(0, 2), (1, 2), (0, 3), (1, 3)],
+            &[(4, 4), (5, 4), (4, 5), (5, 2)],
+        );
+        let mut searcher = TurnSearcher::with_table_size_mb(1);
+        for depth in [1, 2] {
+            let result = searcher.next_turn(board, PieceColor::White, depth, 20);
+            assert!(!result.evaluation.is_end());
+        }
+        for depth in [3, 4, 5] {
+            searcher.clear_table();
+            let result = searcher.next_turn(board, PieceColor::White, depth, 20);
+            assert_eq!(result.evaluation, EvaluationResult::Victory { in_steps: 2 });
+            assert_eq!(result.best_turn(), Some(turn((5, 2), (5, 3))));
+        }
+        assert_eq!(
+            minimax(board, PieceColor::White, 3, 20, RuleSet::CLASSIC),
+            EvaluationResult::Victory { in_steps: 2 }
+        );
+    }
+
+    #[test]
+    fn timed_search_keeps_to_budget() {
+        let budget = Duration::from_millis(50);
+        for threads in [1, 3] {
+            for (i, board) in random_positions(&BoardConfig::CLASSIC, 3)
+                .into_iter()
+                .enumerate()
+            {
+                let color = [PieceColor::White, PieceColor::Black][i % 2];
+                let mut searcher = TurnSearcher::with_table_size_mb(1);
+                searcher.set_threads(threads);
+                let start = Instant::now();
+                let result = searcher.next_turn_timed(board, color, budget, 10);
+                // depth 1 may overrun the budget, but not by much
+                assert!(start.elapsed() < budget + Duration::from_secs(2));
+                let turn = result.best_turn().expect("no turn chosen");
+                assert!(board.is_valid_turn(turn, color), "{board:?} {turn:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn timed_search_finishes_depth_1() {
+        let board = BoardPosition::initial(&BoardConfig::CLASSIC);
+        for budget in [Duration::ZERO, Duration::from_millis(20)] {
+            let mut searcher = TurnSearcher::with_table_size_mb(1);
+            searcher.start_search();
+            searcher.main.deadline = Some(Instant::now() + budget);
+            let (depth, result) =
+                searcher
+                    .main
+                    .iterative_deepening(board, PieceColor::White, MAX_ITERATIVE_DEPTH, 0);
+            assert!(depth >= 1);
+            if budget.is_zero() {
+                assert_eq!(depth, 1);
+            }
+            let turn = result.best_turn().expect("no turn chosen");
+            assert!(board.is_valid_turn(turn, PieceColor::White));
+        }
+    }
+
+    #[test]
+    fn principal_variation_replays() {
+        let positions = random_positions(&BoardConfig::SMALL, 8)
+            .into_iter()
+            .chain(random_positions(&BoardConfig::CLASSIC, 4));
+        for (i, board) in positions.enumerate() {
+            let color = [PieceColor::White, PieceColor::Black][i % 2];
+            let turns_count = 44 + i % 6;
+            if board.has_winner(turns_count) {
+                continue;
+            }
+            for depth in 1..=4 {
+                let result =
+                    TurnSearcher::with_table_size_mb(1).next_turn(board, color, depth, turns_count);
+                let pv = &result.principal_variation;
+                assert!(!pv.is_empty() && pv.len() <= depth, "{board:?} {pv:?}");
+                assert_eq!(pv.first().copied(), result.best_turn());
+
+                let mut board = board;
+                let mut color = color;
+                for (ply, &turn) in pv.iter().enumerate() {
+                    assert_ne!(turn.from, turn.to);
+                    assert!(
+                        board.is_valid_turn(turn, color),
+                        "{ply}: {turn:?} in {board:?}"
+                    );
+                    assert!(!board.has_winner(turns_count + ply));
+                    board = board.perform_turn(turn, color);
+                    color = color.opposite();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn blocked_player_ends_variation() {
+        // without steps pieces with no neighbours can't move, white has the only jump
+        let board = position(
+            &BoardConfig::SMALL,
+            &[(3, 3), (5, 3), (3, 5), (5, 5)],
+            &[(0, 0), (1, 0), (0, 3), (0, 5)],
+        );
+        let jump = turn((0, 0), (2, 0));
+        let mut searcher = TurnSearcher::with_table_size_mb(1);
+        searcher.set_rules(RuleSet::JUMPS_ONLY);
+        for depth in 1..=3 {
+            searcher.clear_table();
+            let result = searcher.next_turn(board, PieceColor::Black, depth, 11);
+            assert_eq!(result.best_turn(), None);
+            assert_eq!(result.evaluation, EvaluationResult::Defeat { in_steps: 0 });
+
+            searcher.clear_table();
+            let result = searcher.next_turn(board, PieceColor::White, depth, 10);
+            assert_eq!(result.principal_variation, [jump], "{depth}");
+            assert_eq!(
+                result.evaluation,
+                minimax(board, PieceColor::White, depth, 10, RuleSet::JUMPS_ONLY)
+            );
+            if depth > 1 {
+                assert_eq!(
+                    result.evaluation,
+                    EvaluationResult::Victory { in_steps: 1 },
+                    "{depth}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn move_ordering_keeps_results() {
+        let positions = random_positions(&BoardConfig::CLASSIC, 5);
+        let mut warm = TurnSearcher::with_table_size_mb(1);
+        for (i, &board) in positions.iter().enumerate() {
+            let color = [PieceColor::White, PieceColor::Black][i % 2];
+            let _ = warm.next_turn(board, color, 3, 10);
+        }
+        assert!(warm.main.history.iter().any(|&score| score > 0));
+
+        for (i, &board) in positions.iter().enumerate().rev() {
+            let color = [PieceColor::White, PieceColor::Black][i % 2];
+            let fresh = TurnSearcher::with_table_size_mb(1).next_turn(board, color, 3, 10);
+            // killers and history from other searches only change the order of turns
+            warm.table.clear();
+            let result = warm.next_turn(board, color, 3, 10);
+            assert_eq!(result.evaluation, fresh.evaluation, "{board:?}");
+        }
+    }
+
+    #[test]
+    fn clear_table_clears_history() {
+        let mut searcher = TurnSearcher::with_table_size_mb(1);
+        searcher.set_threads(2);
+        let _ = searcher.next_turn(
+            BoardPosition::initial(&BoardConfig::CLASSIC),
+            PieceColor::White,
+            4,
+            0,
+        );
+        assert!(searcher.main.history.iter().any(|&score| score > 0));
+        searcher.clear_table();
+        for worker in std::iter::once(&searcher.main).chain(&searcher.helpers) {
+            assert!(worker.history.iter().all(|&score| score == 0));
+        }
+    }
+
+    #[test]
+    fn stalling_policies() {
+        let config = &BoardConfig::SMALL;
+        // one white and two black pieces never left their camps
+        let both_home = position(
+            config,
+            &[(5, 5), (4, 5), (3, 3), (2, 4)],
+            &[(0, 0), (2, 2), (3, 2), (2, 3)],
+        );
+        let even_home = position(
+            config,
+            &[(5, 5), (3, 3), (2, 4), (3, 4)],
+            &[(0, 0), (2, 2), (3, 2), (2, 3)],
+        );
+        let white_home = position(
+            config,
+            &[(3, 3), (2, 4), (3, 4), (4, 3)],
+            &[(0, 0), (2, 2), (3, 2), (2, 3)],
+        );
+        // white filled its target camp around the black piece, which never left
+        let blocked = position(
+            config,
+            &[(5, 5), (2, 0), (3, 0), (2, 1)],
+            &[(4, 4), (5, 4), (4, 5), (2, 2)],
+        );
+        let plies_40 = StallingPolicy {
+            limit: Some(40),
+            unit: TurnsUnit::Plies,
+            ..StallingPolicy::OFFICIAL
+        };
+        let more_pieces = StallingPolicy {
+            penalty: HomePenalty::MorePiecesLoses,
+            ..StallingPolicy::CLASSIC
+        };
+        let black = Some(GameOutcome::Win(PieceColor::Black));
+        let white = Some(GameOutcome::Win(PieceColor::White));
+
+        for (policy, board, turns_count, outcome) in [
+            (StallingPolicy::CLASSIC, both_home, 50, None),
+            // black loses first if both players stalled
+            (StallingPolicy::CLASSIC, both_home, 51, white),
+            (StallingPolicy::CLASSIC, white_home, 51, black),
+            (StallingPolicy::CLASSIC, even_home, 51, white),
+            (StallingPolicy::OFFICIAL, both_home, 51, None),
+            (StallingPolicy::OFFICIAL, both_home, 80, None),
+            (StallingPolicy::OFFICIAL, both_home, 81, white),
+            (StallingPolicy::OFFICIAL, white_home, 81, black),
+            (plies_40, both_home, 40, None),
+            (plies_40, both_home, 41, white),
+            (more_pieces, both_home, 51, white),
+            (more_pieces, white_home, 51, black),
+            (more_pieces, even_home, 51, None),
+            (StallingPolicy::UNLIMITED, both_home, 1000, None),
+            (StallingPolicy::CLASSIC, blocked, 10, None),
+            (StallingPolicy::CLASSIC, blocked, 51, white),
+            (StallingPolicy::OFFICIAL, blocked, 10, white),
+        ] {
+            assert!(board.is_valid_position());
+            let rules = RuleSet {
+                stalling: policy,
+                ..RuleSet::CLASSIC
+            };
+            assert_eq!(
+                board.winner_with(turns_count, rules),
+                outcome,
+                "{policy:?} {turns_count} {board:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn stalling_policy_bytes() {
+        let mut policies = StallingPolicy::VARIANTS.map(|(_, policy)| policy).to_vec();
+        policies.push(StallingPolicy {
+            limit: Some(0),
+            penalty: HomePenalty::MorePiecesLoses,
+            ..StallingPolicy::OFFICIAL
+        });
+        policies.push(StallingPolicy {
+            limit: Some(u16::MAX - 1),
+            blocked_camp: BlockedCamp::Finished,
+            ..StallingPolicy::CLASSIC
+        });
+        for policy in policies {
+            assert_eq!(StallingPolicy::from_bytes(policy.to_bytes()), Some(policy));
+        }
+        assert_eq!(StallingPolicy::CLASSIC.to_bytes()[0], 0);
+        assert_eq!(StallingPolicy::from_bytes([8, 50, 0]), None);
+    }
+
+    #[test]
+    fn turn_errors() {
+        let board = BoardPosition::initial(&BoardConfig::CLASSIC);
+        let white = PieceColor::White;
+        let off_board = Turn {
+            from: Position::from_xy(2, 0),
+            to: Position::from_xy(8, 0),
         };
 
-        self.cashe
-            .entry((player_color, board))
-            .and_modify(|v| *v = choose(*v, ret))
-            .or_insert(ret);
+        for (turn, rules, error) in [
+            (turn((3, 3), (3, 4)), RuleSet::CLASSIC, TurnError::NoPiece),
+            (
+                turn((5, 5), (4, 5)),
+                RuleSet::CLASSIC,
+                TurnError::WrongColor,
+            ),
+            (
+                turn((0, 0), (1, 0)),
+                RuleSet::CLASSIC,
+                TurnError::DestinationOccupied,
+            ),
+            (off_board, RuleSet::CLASSIC, TurnError::OutOfBoard),
+            // empty and two squares away, but there is nothing to jump over
+            (
+                turn((2, 0), (4, 0)),
+                RuleSet::CLASSIC,
+                TurnError::Unreachable,
+            ),
+            // diagonal neighbour
+            (
+                turn((2, 2), (3, 3)),
+                RuleSet::CLASSIC,
+                TurnError::Unreachable,
+            ),
+            // jump over own piece
+            (
+                turn((1, 0), (3, 0)),
+                RuleSet::OPPONENT_JUMPS,
+                TurnError::Unreachable,
+            ),
+        ] {
+            assert_eq!(
+                board.check_turn_with(turn, white, rules),
+                Err(error),
+                "{turn:?}"
+            );
+            assert_eq!(
+                board.try_perform_turn_with(turn, white, rules),
+                Err(error),
+                "{turn:?}"
+            );
+        }
 
-        ret
+        for (turn, rules) in [
+            (turn((2, 0), (3, 0)), RuleSet::CLASSIC),
+            (turn((1, 0), (3, 0)), RuleSet::CLASSIC),
+            (turn((2, 2), (3, 3)), RuleSet::DIAGONAL),
+        ] {
+            assert_eq!(
+                board.try_perform_turn_with(turn, white, rules),
+                Ok(board.perform_turn(turn, white))
+            );
+        }
     }
-}
 
-impl Default for TurnSearcher {
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn predefined_configs() {
+        for config in BoardConfig::VARIANTS {
+            let squares = config.squares();
+            assert_eq!(
+                squares.count(),
+                config.width as u32 * config.height as u32,
+                "{config}"
+            );
+            for color in [PieceColor::White, PieceColor::Black] {
+                let camp = config.starting_camp(color);
+                assert_eq!(camp.count(), config.pieces(), "{config}");
+                assert_eq!(camp - squares, PositionMask::EMPTY, "{config}");
+                assert_eq!(config.target_camp(color.opposite()), camp);
+            }
+            assert_eq!(
+                config.starting_camp(PieceColor::White) & config.starting_camp(PieceColor::Black),
+                PositionMask::EMPTY
+            );
+            assert!(BoardPosition::initial(config).is_valid_position());
+            assert_eq!(BoardConfig::from_name(&config.to_string()), Some(config));
+        }
+    }
+
+    #[test]
+    fn positions_follow_config_width() {
+        for config in BoardConfig::VARIANTS {
+            for y in 0..Position::MAX_HEIGHT {
+                for x in 0..Position::STRIDE {
+                    let pos = Position::from_xy(x, y);
+                    assert_eq!((pos.get_x(), pos.get_y()), (x, y));
+                    assert_eq!(
+                        config.contains(pos),
+                        x < config.width && y < config.height,
+                        "{config} {x} {y}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "camp_width <= width")]
+    fn camp_wider_than_board() {
+        let _ = BoardConfig::new(6, 6, 7, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "camp_width <= width")]
+    fn camp_taller_than_board() {
+        let _ = BoardConfig::new(6, 6, 1, 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "height <= Position::MAX_HEIGHT")]
+    fn board_taller_than_encoding() {
+        let _ = BoardConfig::new(6, Position::MAX_HEIGHT + 1, 2, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "width <= Position::STRIDE")]
+    fn board_wider_than_encoding() {
+        let _ = BoardConfig::new(Position::STRIDE + 1, 6, 2, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "2 * camp_width <= width")]
+    fn overlapping_camps() {
+        let _ = BoardConfig::new(6, 6, 4, 4);
     }
 }
@@ -0,0 +1,244 @@
+//! Algebraic notation of positions and turns
+//!
+//! Square is a file letter followed by a rank number: files `a`, `b`, ... go along x
+//! (left to right in the game), ranks `1`, `2`, ... go along y. The game draws the board
+//! with `flip_y`, so rank 1 is the bottom row and `a1` (`x = 0`, `y = 0`) is the bottom
+//! left corner, like on a chess board.
+//!
+//! Turn is written as `c3-c4` (from and to squares, a step or a turn with unknown path),
+//! path of jumps is written as the chain of visited squares `c3:e3:e5`.
+
+use crate::path::TurnPath;
+use crate::{Position, Turn};
+use std::str::FromStr;
+
+/// Reason the notation can't be parsed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NotationError {
+    /// File letter isn't one of the supported board columns
+    InvalidFile,
+    /// Rank is missing, isn't a number or is above the supported board rows
+    InvalidRank,
+    /// Turn doesn't have two squares separated by `-` or `:`
+    InvalidTurn,
+    /// Squares separated by `-` in a path aren't neighbours
+    NotAStep,
+    /// Squares separated by `:` aren't two squares apart in a line
+    NotAJump,
+}
+
+impl std::fmt::Display for NotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::InvalidFile => "invalid file letter",
+            Self::InvalidRank => "invalid rank number",
+            Self::InvalidTurn => "turn should be `from-to` or chain of jumps `from:over:to`",
+            Self::NotAStep => "step should go to the neighbour square",
+            Self::NotAJump => "jump should go two squares in a line",
+        })
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.get_x()) as char, self.get_y() + 1)
+    }
+}
+
+impl FromStr for Position {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, NotationError> {
+        let mut chars = s.chars();
+        let x = chars
+            .next()
+            .filter(char::is_ascii_lowercase)
+            .map(|file| file as u8 - b'a')
+            .filter(|x| *x < Position::STRIDE)
+            .ok_or(NotationError::InvalidFile)?;
+        let rank = chars.as_str();
+        // no signs or leading zeros
+        if rank.starts_with('0') || !rank.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(NotationError::InvalidRank);
+        }
+        let y = rank
+            .parse::<u8>()
+            .ok()
+            .filter(|y| (1..=Position::MAX_HEIGHT).contains(y))
+            .ok_or(NotationError::InvalidRank)?;
+        Ok(Position::from_xy(x, y - 1))
+    }
+}
+
+impl std::fmt::Display for Turn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.from, self.to)
+    }
+}
+
+/// Parses `from-to` as well as the chain of jumps (taking its ends)
+impl FromStr for Turn {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, NotationError> {
+        if let Some((from, to)) = s.split_once('-') {
+            return Ok(Turn {
+                from: from.parse()?,
+                to: to.parse()?,
+            });
+        }
+        Ok(s.parse::<TurnPath>()?.turn())
+    }
+}
+
+/// Step as `c3-c4`, chain of jumps as `c3:e3:e5`
+impl std::fmt::Display for TurnPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let separator = if self.is_jump() { ':' } else { '-' };
+        for (i, pos) in self.positions().iter().enumerate() {
+            if i > 0 {
+                write!(f, "{separator}")?;
+            }
+            write!(f, "{pos}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks only the shape of steps and jumps, paths possible on the board are given by
+/// [`BoardPosition::paths_for`](crate::BoardPosition::paths_for)
+impl FromStr for TurnPath {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, NotationError> {
+        if let Some((from, to)) = s.split_once('-') {
+            let (from, to) = (from.parse()?, to.parse()?);
+            return match distance(from, to) {
+                (0, 1) | (1, 0) | (1, 1) => Ok(TurnPath(vec![from, to])),
+                _ => Err(NotationError::NotAStep),
+            };
+        }
+
+        let positions = s
+            .split(':')
+            .map(Position::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        check_jumps(&positions)?;
+        Ok(TurnPath(positions))
+    }
+}
+
+/// Path of the visited squares with the same checks as [`TurnPath::from_str`]
+#[cfg(feature = "serde")]
+pub(crate) fn path_from_positions(positions: Vec<Position>) -> Result<TurnPath, NotationError> {
+    if let [from, to] = positions[..] {
+        if matches!(distance(from, to), (0, 1) | (1, 0) | (1, 1)) {
+            return Ok(TurnPath(positions));
+        }
+    }
+    check_jumps(&positions)?;
+    Ok(TurnPath(positions))
+}
+
+#[inline]
+fn distance(from: Position, to: Position) -> (u8, u8) {
+    (
+        from.get_x().abs_diff(to.get_x()),
+        from.get_y().abs_diff(to.get_y()),
+    )
+}
+
+fn check_jumps(positions: &[Position]) -> Result<(), NotationError> {
+    if positions.len() < 2 {
+        return Err(NotationError::InvalidTurn);
+    }
+    for pair in positions.windows(2) {
+        if !matches!(distance(pair[0], pair[1]), (0, 2) | (2, 0) | (2, 2)) {
+            return Err(NotationError::NotAJump);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoardConfig, BoardPosition, PieceColor, RuleSet};
+
+    #[test]
+    fn squares_round_trip() {
+        for y in 0..Position::MAX_HEIGHT {
+            for x in 0..Position::STRIDE {
+                let pos = Position::from_xy(x, y);
+                assert_eq!(pos.to_string().parse(), Ok(pos));
+            }
+        }
+        assert_eq!(Position::from_xy(2, 0).to_string(), "c1");
+        assert_eq!("j12".parse(), Ok(Position::from_xy(9, 11)));
+    }
+
+    #[test]
+    fn invalid_squares() {
+        for (text, err) in [
+            ("", NotationError::InvalidFile),
+            ("C3", NotationError::InvalidFile),
+            ("k3", NotationError::InvalidFile),
+            ("c", NotationError::InvalidRank),
+            ("c0", NotationError::InvalidRank),
+            ("c03", NotationError::InvalidRank),
+            ("c+3", NotationError::InvalidRank),
+            ("c13", NotationError::InvalidRank),
+        ] {
+            assert_eq!(text.parse::<Position>(), Err(err), "{text}");
+        }
+    }
+
+    #[test]
+    fn turns_and_paths() {
+        let turn = "c3:e3:e5".parse::<Turn>().unwrap();
+        assert_eq!(turn.to_string(), "c3-e5");
+        assert_eq!(turn, "c3-e5".parse().unwrap());
+
+        for (text, err) in [
+            ("c3", NotationError::InvalidTurn),
+            ("c3-c5", NotationError::NotAStep),
+            ("c3:c4", NotationError::NotAJump),
+            ("c3:e3:e4", NotationError::NotAJump),
+            ("c3-x4", NotationError::InvalidFile),
+        ] {
+            assert_eq!(text.parse::<TurnPath>(), Err(err), "{text}");
+        }
+        assert_eq!(
+            "c3-d4".parse::<TurnPath>().map(|path| path.is_jump()),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn game_paths_round_trip() {
+        for config in BoardConfig::VARIANTS {
+            for (_, rules) in RuleSet::VARIANTS {
+                let mut board = BoardPosition::initial(config);
+                let mut side = PieceColor::White;
+                for i in 0..40 {
+                    let turns = board
+                        .generate_all_turns_for_with(side, rules)
+                        .collect::<Vec<_>>();
+                    if turns.is_empty() {
+                        break;
+                    }
+                    for &turn in &turns {
+                        let path = board.path_for_with(turn, rules).unwrap();
+                        assert_eq!(path.to_string().parse(), Ok(path.clone()));
+                        assert_eq!(path.to_string().parse(), Ok(turn));
+                    }
+                    board = board.perform_turn(turns[i * 7 % turns.len()], side);
+                    side = side.opposite();
+                }
+            }
+        }
+    }
+}
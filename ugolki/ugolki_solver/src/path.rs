@@ -0,0 +1,321 @@
+//! Paths of the pieces: single step or chain of jumps leading to the turn destination
+
+use crate::{BoardPosition, JumpOver, PieceColor, Position, PositionMask, RuleSet, Turn};
+use std::collections::VecDeque;
+
+type Direction = fn(Position) -> Option<Position>;
+
+/// Position after jump from `pos` in `direction`
+#[inline]
+fn jump(
+    pos: Position,
+    direction: Direction,
+    jump_over: PositionMask,
+    free: PositionMask,
+) -> Option<Position> {
+    let over = direction(pos)?;
+    let to = direction(over)?;
+    (jump_over.has_piece_at(over) && free.has_piece_at(to)).then_some(to)
+}
+
+impl BoardPosition {
+    /// Pieces the piece at `from` can jump over and free positions
+    #[inline]
+    fn jump_masks(self, from: Position, rules: RuleSet) -> (PositionMask, PositionMask) {
+        let occupied = self.all_pieces_mask();
+        let jump_over = match rules.jump_over {
+            JumpOver::Any => occupied,
+            JumpOver::Opponent if self.0[PieceColor::Black as usize].has_piece_at(from) => {
+                self.0[PieceColor::White as usize]
+            }
+            JumpOver::Opponent => self.0[PieceColor::Black as usize],
+        };
+        (jump_over, self.config().squares() - occupied)
+    }
+
+    /// Checks positions and that the turn can be a single step
+    #[inline]
+    fn path_start(self, turn: Turn, rules: RuleSet) -> (bool, Option<TurnPath>) {
+        let config = self.config();
+        let has_piece = config.contains(turn.from)
+            && config.contains(turn.to)
+            && self.all_pieces_mask().has_piece_at(turn.from)
+            && !self.all_pieces_mask().has_piece_at(turn.to);
+        let step = (has_piece
+            && rules.steps
+            && rules
+                .directions()
+                .iter()
+                .any(|step| step(turn.from) == Some(turn.to)))
+        .then(|| TurnPath(vec![turn.from, turn.to]));
+        (has_piece, step)
+    }
+}
+
+/// Positions visited by the piece during the turn, starting with `from` and ending with `to`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TurnPath(pub(crate) Vec<Position>);
+
+impl TurnPath {
+    #[must_use]
+    #[inline]
+    pub fn positions(&self) -> &[Position] {
+        &self.0
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn turn(&self) -> Turn {
+        Turn {
+            from: self.0[0],
+            to: self.0[self.0.len() - 1],
+        }
+    }
+
+    /// Number of steps or jumps
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len() - 1
+    }
+
+    /// Always `false`, path has at least one step
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.len() < 2
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_jump(&self) -> bool {
+        let (from, to) = (self.0[0], self.0[1]);
+        from.get_x()
+            .abs_diff(to.get_x())
+            .max(from.get_y().abs_diff(to.get_y()))
+            == 2
+    }
+
+    /// Single steps or jumps of the path as turns
+    #[inline]
+    pub fn steps(&self) -> impl Iterator<Item = Turn> + '_ {
+        self.0.windows(2).map(|pair| Turn {
+            from: pair[0],
+            to: pair[1],
+        })
+    }
+}
+
+/// Iterator over distinct paths of the turn, see [`BoardPosition::paths_for`]
+#[derive(Debug, Clone)]
+pub struct TurnPaths {
+    jump_over: PositionMask,
+    free: PositionMask,
+    directions: &'static [Direction],
+    to: Position,
+    step: Option<TurnPath>,
+    path: Vec<Position>,
+    /// Next direction to try from each position of the path
+    next_directions: Vec<usize>,
+    visited: PositionMask,
+}
+
+impl Iterator for TurnPaths {
+    type Item = TurnPath;
+
+    fn next(&mut self) -> Option<TurnPath> {
+        if let Some(step) = self.step.take() {
+            return Some(step);
+        }
+
+        while let (Some(&pos), Some(direction)) =
+            (self.path.last(), self.next_directions.last_mut())
+        {
+            // path can't go through the destination and come back to it
+            if *direction == self.directions.len() || (pos == self.to && self.path.len() > 1) {
+                self.path.pop();
+                self.next_directions.pop();
+                self.visited.remove_position(pos);
+                continue;
+            }
+
+            let next = jump(pos, self.directions[*direction], self.jump_over, self.free);
+            *direction += 1;
+            if let Some(next) = next.filter(|next| !self.visited.has_piece_at(*next)) {
+                self.path.push(next);
+                self.next_directions.push(0);
+                self.visited.add_position(next);
+                if next == self.to {
+                    return Some(TurnPath(self.path.clone()));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl BoardPosition {
+    /// All distinct paths of the piece at `turn.from` to `turn.to`
+    ///
+    /// Step comes first, then chains of jumps (never visiting the same position twice)
+    /// in depth-first order. Empty if there is no piece at `turn.from` or the turn is impossible.
+    #[must_use]
+    pub fn paths_for(self, turn: Turn) -> TurnPaths {
+        self.paths_for_with(turn, RuleSet::default())
+    }
+
+    /// [`paths_for`](Self::paths_for) under `rules`
+    #[must_use]
+    pub fn paths_for_with(self, turn: Turn, rules: RuleSet) -> TurnPaths {
+        let (has_piece, step) = self.path_start(turn, rules);
+        let (jump_over, free) = self.jump_masks(turn.from, rules);
+
+        TurnPaths {
+            jump_over,
+            free,
+            directions: rules.directions(),
+            to: turn.to,
+            step,
+            path: if has_piece {
+                vec![turn.from]
+            } else {
+                Vec::new()
+            },
+            next_directions: if has_piece { vec![0] } else { Vec::new() },
+            visited: if has_piece {
+                PositionMask::from_position(turn.from)
+            } else {
+                PositionMask::EMPTY
+            },
+        }
+    }
+
+    /// Canonical path of the turn: step if possible, otherwise the shortest chain of jumps
+    ///
+    /// Ties are broken by direction order (up, left, down, right, then the diagonal ones
+    /// if allowed) of the earliest jumps.
+    #[must_use]
+    pub fn path_for(self, turn: Turn) -> Option<TurnPath> {
+        self.path_for_with(turn, RuleSet::default())
+    }
+
+    /// [`path_for`](Self::path_for) under `rules`
+    #[must_use]
+    pub fn path_for_with(self, turn: Turn, rules: RuleSet) -> Option<TurnPath> {
+        let (has_piece, step) = self.path_start(turn, rules);
+        if !has_piece {
+            return None;
+        }
+        if step.is_some() {
+            return step;
+        }
+
+        let (jump_over, free) = self.jump_masks(turn.from, rules);
+        let mut previous = [None; Position::COUNT];
+        let mut visited = PositionMask::from_position(turn.from);
+        let mut queue = VecDeque::from([turn.from]);
+        while let Some(pos) = queue.pop_front() {
+            if pos == turn.to {
+                let mut path = vec![pos];
+                while let Some(prev) = previous[path[path.len() - 1].0 as usize] {
+                    path.push(prev);
+                }
+                path.reverse();
+                return Some(TurnPath(path));
+            }
+            for direction in rules.directions() {
+                if let Some(next) = jump(pos, *direction, jump_over, free) {
+                    if !visited.has_piece_at(next) {
+                        visited.add_position(next);
+                        previous[next.0 as usize] = Some(pos);
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::position;
+    use crate::BoardConfig;
+
+    fn turn((from_x, from_y): (u8, u8), (to_x, to_y): (u8, u8)) -> Turn {
+        Turn {
+            from: Position::from_xy(from_x, from_y),
+            to: Position::from_xy(to_x, to_y),
+        }
+    }
+
+    fn squares(path: &TurnPath) -> Vec<(u8, u8)> {
+        path.positions()
+            .iter()
+            .map(|pos| (pos.get_x(), pos.get_y()))
+            .collect()
+    }
+
+    /// Piece at (0, 2) jumps to (2, 2) right away or around the loop of pieces
+    fn loop_position() -> BoardPosition {
+        position(
+            &BoardConfig::CLASSIC,
+            &[(1, 2), (0, 3), (1, 4), (2, 3)],
+            &[(0, 2)],
+        )
+    }
+
+    #[test]
+    fn distinct_jump_chains() {
+        let board = loop_position();
+        let paths = board.paths_for(turn((0, 2), (2, 2))).collect::<Vec<_>>();
+        assert_eq!(
+            paths.iter().map(squares).collect::<Vec<_>>(),
+            [vec![(0, 2), (0, 4), (2, 4), (2, 2)], vec![(0, 2), (2, 2)],]
+        );
+        for path in &paths {
+            assert!(path.is_jump());
+            assert_eq!(path.turn(), turn((0, 2), (2, 2)));
+            let mut visited = PositionMask::EMPTY;
+            for &pos in path.positions() {
+                assert!(!visited.has_piece_at(pos), "{path:?}");
+                visited.add_position(pos);
+            }
+        }
+
+        let shortest = board.path_for(turn((0, 2), (2, 2))).unwrap();
+        assert_eq!(squares(&shortest), [(0, 2), (2, 2)]);
+        assert_eq!(shortest.len(), 1);
+    }
+
+    #[test]
+    fn single_step() {
+        let board = loop_position();
+        let step = board.path_for(turn((0, 2), (0, 1))).unwrap();
+        assert_eq!(squares(&step), [(0, 2), (0, 1)]);
+        assert!(!step.is_jump());
+        assert_eq!(step.steps().collect::<Vec<_>>(), [turn((0, 2), (0, 1))]);
+        assert_eq!(
+            board.paths_for(turn((0, 2), (0, 1))).collect::<Vec<_>>(),
+            [step]
+        );
+    }
+
+    #[test]
+    fn unreachable_turns() {
+        let board = loop_position();
+        for unreachable in [
+            // empty, but no jump lands there
+            turn((0, 2), (2, 0)),
+            turn((0, 2), (4, 2)),
+            // no piece to move
+            turn((5, 5), (5, 6)),
+            // destination is taken
+            turn((0, 2), (1, 2)),
+        ] {
+            assert_eq!(board.path_for(unreachable), None, "{unreachable:?}");
+            assert_eq!(board.paths_for(unreachable).count(), 0, "{unreachable:?}");
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! Move generation counters, regression baseline for the turns generator
+//!
+//! Perft counts all turn sequences of given length. Finished games are not
+//! detected, so the counts depend on the turns generator only.
+
+use crate::{BoardConfig, BoardPosition, PieceColor, PositionMask, RuleSet, Turn};
+
+/// Number of turn sequences with `depth` turns under classic rules, starting with `color`
+#[must_use]
+#[inline]
+pub fn perft(board: BoardPosition, color: PieceColor, depth: usize) -> u64 {
+    perft_with(board, color, RuleSet::CLASSIC, depth)
+}
+
+/// [`perft`] under `rules`
+#[must_use]
+pub fn perft_with(board: BoardPosition, color: PieceColor, rules: RuleSet, depth: usize) -> u64 {
+    match depth {
+        0 => 1,
+        1 => board.generate_all_turns_for_with(color, rules).count() as u64,
+        _ => board
+            .generate_all_turns_for_with(color, rules)
+            .map(|turn| {
+                perft_with(
+                    board.perform_turn(turn, color),
+                    color.opposite(),
+                    rules,
+                    depth - 1,
+                )
+            })
+            .sum(),
+    }
+}
+
+/// [`perft`] for each root turn, in the turns generation order
+#[must_use]
+#[inline]
+pub fn perft_divide(board: BoardPosition, color: PieceColor, depth: usize) -> Vec<(Turn, u64)> {
+    perft_divide_with(board, color, RuleSet::CLASSIC, depth)
+}
+
+/// [`perft_divide`] under `rules`
+#[must_use]
+pub fn perft_divide_with(
+    board: BoardPosition,
+    color: PieceColor,
+    rules: RuleSet,
+    depth: usize,
+) -> Vec<(Turn, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    board
+        .generate_all_turns_for_with(color, rules)
+        .map(|turn| {
+            let count = perft_with(
+                board.perform_turn(turn, color),
+                color.opposite(),
+                rules,
+                depth - 1,
+            );
+            (turn, count)
+        })
+        .collect()
+}
+
+/// Position with known perft counts
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PerftReference {
+    pub name: &'static str,
+    pub board: BoardPosition,
+    pub side_to_move: PieceColor,
+    pub rules: RuleSet,
+    /// `counts[i]` is the perft with depth `i + 1`
+    pub counts: &'static [u64],
+}
+
+const MIDDLEGAME: BoardPosition = BoardPosition(
+    [
+        PositionMask(0x0005_0000_4412_0060_0002),
+        PositionMask(0x0408_0160_1000_8000_2800),
+    ],
+    &BoardConfig::CLASSIC,
+);
+
+/// Counts of the current turns generator, depth 5 of the later positions takes
+/// a few seconds in release build
+///
+/// Counts of the classic board positions under classic rules (the first five) up to
+/// depth 5 are the same as the ones of the recursive jump search which preceded the
+/// current generator.
+pub const PERFT_REFERENCES: &[PerftReference] = &[
+    PerftReference {
+        name: "initial",
+        board: BoardPosition::initial(&BoardConfig::CLASSIC),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::CLASSIC,
+        counts: &[12, 144, 2784, 53824, 1_135_552],
+    },
+    PerftReference {
+        name: "initial, black to move",
+        board: BoardPosition::initial(&BoardConfig::CLASSIC),
+        side_to_move: PieceColor::Black,
+        rules: RuleSet::CLASSIC,
+        counts: &[12, 144, 2784, 53824, 1_135_552],
+    },
+    PerftReference {
+        name: "opening",
+        board: BoardPosition(
+            [
+                PositionMask(0x2404_0040_b808_0000_0000),
+                PositionMask(0x0080_0005_8190_0005),
+            ],
+            &BoardConfig::CLASSIC,
+        ),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::CLASSIC,
+        counts: &[35, 1120, 38251, 1_269_880, 43_535_674],
+    },
+    PerftReference {
+        name: "middlegame",
+        board: MIDDLEGAME,
+        side_to_move: PieceColor::White,
+        rules: RuleSet::CLASSIC,
+        counts: &[41, 1358, 53517, 1_877_559, 73_137_233],
+    },
+    PerftReference {
+        name: "crossed camps",
+        board: BoardPosition(
+            [
+                PositionMask(0x0d01_0660_0002),
+                PositionMask(0x2408_8158_1000_0000_0000),
+            ],
+            &BoardConfig::CLASSIC,
+        ),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::CLASSIC,
+        counts: &[45, 1437, 56560, 1_906_534, 71_095_944],
+    },
+    PerftReference {
+        name: "6x6/4 initial",
+        board: BoardPosition::initial(&BoardConfig::SMALL),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::CLASSIC,
+        counts: &[8, 64, 720, 8100, 90986],
+    },
+    PerftReference {
+        name: "8x8/12 initial",
+        board: BoardPosition::initial(&BoardConfig::CLASSIC_12),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::CLASSIC,
+        counts: &[14, 196, 4412, 99597, 2_559_237],
+    },
+    PerftReference {
+        name: "10x10/16 initial",
+        board: BoardPosition::initial(&BoardConfig::LARGE),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::CLASSIC,
+        counts: &[16, 256, 6496, 164_836, 4_898_970],
+    },
+    PerftReference {
+        name: "initial, diagonal",
+        board: BoardPosition::initial(&BoardConfig::CLASSIC),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::DIAGONAL,
+        counts: &[26, 677, 23547, 828_210, 33_590_027],
+    },
+    PerftReference {
+        name: "middlegame, diagonal",
+        board: MIDDLEGAME,
+        side_to_move: PieceColor::White,
+        rules: RuleSet::DIAGONAL,
+        counts: &[69, 5210, 396_786, 30_938_704],
+    },
+    PerftReference {
+        name: "initial, jumps only",
+        board: BoardPosition::initial(&BoardConfig::CLASSIC),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::JUMPS_ONLY,
+        counts: &[6, 36, 300, 2500, 21308],
+    },
+    PerftReference {
+        name: "middlegame, jumps only",
+        board: MIDDLEGAME,
+        side_to_move: PieceColor::White,
+        rules: RuleSet::JUMPS_ONLY,
+        counts: &[19, 188, 3525, 50419, 977_926],
+    },
+    PerftReference {
+        name: "initial, opponent jumps",
+        board: BoardPosition::initial(&BoardConfig::CLASSIC),
+        side_to_move: PieceColor::White,
+        rules: RuleSet::OPPONENT_JUMPS,
+        counts: &[6, 36, 384, 4096, 46080],
+    },
+    PerftReference {
+        name: "middlegame, opponent jumps",
+        board: MIDDLEGAME,
+        side_to_move: PieceColor::White,
+        rules: RuleSet::OPPONENT_JUMPS,
+        counts: &[25, 617, 16052, 410_501, 10_944_071],
+    },
+];
+
+/// Reference count differing from the computed one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PerftMismatch {
+    pub name: &'static str,
+    pub depth: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl std::fmt::Display for PerftMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "perft of `{}` at depth {}: expected {}, got {}",
+            self.name, self.depth, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for PerftMismatch {}
+
+/// Checks [`PERFT_REFERENCES`] counts up to `max_depth`
+pub fn verify_references(max_depth: usize) -> Result<(), PerftMismatch> {
+    for reference in PERFT_REFERENCES {
+        for (i, expected) in reference.counts.iter().take(max_depth).enumerate() {
+            let actual = perft_with(
+                reference.board,
+                reference.side_to_move,
+                reference.rules,
+                i + 1,
+            );
+            if actual != *expected {
+                return Err(PerftMismatch {
+                    name: reference.name,
+                    depth: i + 1,
+                    expected: *expected,
+                    actual,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_counts_match() {
+        assert_eq!(verify_references(3), Ok(()));
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        for reference in PERFT_REFERENCES {
+            let divide =
+                perft_divide_with(reference.board, reference.side_to_move, reference.rules, 2);
+            assert_eq!(divide.len() as u64, reference.counts[0]);
+            assert_eq!(
+                divide.iter().map(|(_, count)| count).sum::<u64>(),
+                reference.counts[1],
+                "{}",
+                reference.name
+            );
+        }
+    }
+}
@@ -0,0 +1,682 @@
+//! Game records in portable game notation (like PGN in chess)
+//!
+//! Game starts with tag pairs, one per line, followed by the numbered turns and the result:
+//!
+//! ```text
+//! [Event "Casual game"]
+//! [Date "2024.05.01"]
+//! [White "Player"]
+//! [Black "Engine"]
+//! [TimeControl "1.5s"]
+//! [Variant "8x8/9"]
+//! [Result "0-1"]
+//!
+//! 1. c3-c4 f6-f5 {[%eval 120] book} 2. a3:c3:c5 ... 0-1
+//! ```
+//!
+//! - `Variant` is the board name followed by the rules in [`fen`](crate::fen) format (classic
+//!   if omitted)
+//! - `FEN` is the starting position, only present if it isn't the initial one
+//! - `Result` is `1-0` (White won), `0-1` (Black won), `1/2-1/2` (draw) or `*` (no result),
+//!   the same marker ends the turns
+//! - turns are in [`notation`](crate::notation), numbers are counted from the initial position
+//!   like in chess: `N.` goes before White's turn, `N...` before Black's turn at the start or
+//!   after a comment
+//! - comment in braces follows the turn (or the start of the game), `[%eval ...]` in it is the
+//!   evaluation for the player who made the turn: score, `#n` for victory in `n` turns, `-#n`
+//!   for defeat or `draw`. Comment at the start is kept as it is, with the evaluation if any.
+//!   Comments can't contain `}`
+//!
+//! Other tags (players, date, time control, ...) are kept as they are. Text may contain
+//! several games, see [`parse_games`].
+
+use crate::fen::{parse_rules, rules_name, Fen, FenError};
+use crate::game::Game;
+use crate::notation::NotationError;
+use crate::{BoardConfig, EvaluationResult, GameOutcome, PieceColor, RuleSet, Turn};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// Reason the game record can't be read
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PgnError {
+    /// Tag isn't `[Name "value"]`
+    InvalidTag,
+    /// `Variant` tag has unknown board or rules
+    InvalidVariant,
+    InvalidFen(FenError),
+    /// `FEN` position doesn't match the `Variant` tag
+    VariantMismatch,
+    InvalidTurn(NotationError),
+    /// `[%eval ...]` isn't a score, `#n`, `-#n` or `draw`
+    InvalidEvaluation,
+    /// Comment isn't closed with `}`
+    UnclosedComment,
+    /// Turns aren't ended with the result marker
+    MissingResult,
+    /// `Result` tag, result marker and the final position disagree
+    ResultMismatch,
+    /// Turn (made at `turns_count` plies) isn't possible in the position or the game is finished
+    IllegalTurn {
+        turns_count: usize,
+        turn: Turn,
+    },
+    /// Text after the game where the single game is expected
+    TrailingText,
+}
+
+impl std::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidTag => f.write_str("tag should be `[Name \"value\"]`"),
+            Self::InvalidVariant => f.write_str("unknown variant"),
+            Self::InvalidFen(err) => write!(f, "invalid FEN: {err}"),
+            Self::VariantMismatch => f.write_str("FEN doesn't match the variant"),
+            Self::InvalidTurn(err) => write!(f, "invalid turn: {err}"),
+            Self::InvalidEvaluation => f.write_str("invalid evaluation"),
+            Self::UnclosedComment => f.write_str("comment isn't closed"),
+            Self::MissingResult => f.write_str("turns should end with the result"),
+            Self::ResultMismatch => f.write_str("result doesn't match the game"),
+            Self::IllegalTurn { turns_count, turn } => {
+                write!(f, "illegal turn {turn} after {turns_count} turns")
+            }
+            Self::TrailingText => f.write_str("unexpected text after the game"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/// Turn with optional evaluation and comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnnotatedTurn {
+    pub turn: Turn,
+    /// Evaluation for the player who made the turn
+    pub evaluation: Option<EvaluationResult>,
+    pub comment: Option<String>,
+}
+
+impl From<Turn> for AnnotatedTurn {
+    #[inline]
+    fn from(turn: Turn) -> Self {
+        Self {
+            turn,
+            evaluation: None,
+            comment: None,
+        }
+    }
+}
+
+/// Game record with tags and annotated turns
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PgnGame {
+    /// Tags in the written order, except `Variant`, `FEN` and `Result` which are given by
+    /// `start` and `result`
+    pub tags: Vec<(String, String)>,
+    pub start: Fen,
+    /// Comment before the first turn, `[%eval ...]` in it isn't parsed
+    pub comment: Option<String>,
+    pub turns: Vec<AnnotatedTurn>,
+    /// `None` if the game isn't finished or its result is unknown
+    pub result: Option<GameOutcome>,
+}
+
+impl PgnGame {
+    /// Record of the played (not undone) turns of `game` with its result and no tags
+    #[must_use]
+    pub fn from_game(game: &Game) -> Self {
+        Self {
+            tags: Vec::new(),
+            start: Fen {
+                board: game.start(),
+                side_to_move: game.start_side(),
+                turns_count: game.start_turns_count(),
+                rules: game.rules(),
+            },
+            comment: None,
+            turns: game
+                .turns()
+                .iter()
+                .copied()
+                .map(AnnotatedTurn::from)
+                .collect(),
+            result: game.winner(),
+        }
+    }
+
+    /// Value of the tag `name`
+    #[must_use]
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces the value of the tag `name` or adds it to the end
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value,
+            None => self.tags.push((name.to_owned(), value)),
+        }
+    }
+
+    /// Plays the turns from the start checking each one against
+    /// [`generate_all_turns_for_with`](crate::BoardPosition::generate_all_turns_for_with)
+    ///
+    /// Result should match the final position if the game is finished there.
+    pub fn replay(&self) -> Result<Game, PgnError> {
+        let mut game = Game::from_fen(self.start);
+        for annotated in &self.turns {
+            let turn = annotated.turn;
+            let legal = !game.is_finished()
+                && game
+                    .board()
+                    .generate_all_turns_for_with(game.side_to_move(), game.rules())
+                    .any(|generated| generated == turn);
+            if !legal {
+                return Err(PgnError::IllegalTurn {
+                    turns_count: game.turns_count(),
+                    turn,
+                });
+            }
+            game.play(turn).expect("generated turn is legal");
+        }
+
+        if let Some(outcome) = game.winner() {
+            if self.result != Some(outcome) {
+                return Err(PgnError::ResultMismatch);
+            }
+        }
+        Ok(game)
+    }
+}
+
+impl std::fmt::Display for PgnGame {
+    /// Writes the game without checking its turns, jumps are written with their paths
+    ///
+    /// After the first illegal turn the position isn't known, so the rest of the turns is
+    /// written with their end squares only.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, value) in &self.tags {
+            if !matches!(name.as_str(), "Variant" | "FEN" | "Result") {
+                write_tag(f, name, value)?;
+            }
+        }
+        let config = self.start.board.config();
+        let variant = match rules_name(self.start.rules) {
+            Some(rules) => format!("{config} {rules}"),
+            None => config.to_string(),
+        };
+        write_tag(f, "Variant", &variant)?;
+        if self.start != Fen::initial(config, self.start.rules) {
+            write_tag(f, "FEN", &self.start.to_string())?;
+        }
+        let result = result_marker(self.result);
+        write_tag(f, "Result", result)?;
+        writeln!(f)?;
+
+        let mut line = Line::default();
+        if let Some(comment) = &self.comment {
+            line.push(f, &format!("{{{comment}}}"))?;
+        }
+        let mut board = Some(self.start.board);
+        let mut side = self.start.side_to_move;
+        let mut need_number = true;
+        for (i, annotated) in self.turns.iter().enumerate() {
+            let turns_count = self.start.turns_count + i;
+            let number = turns_count / 2 + 1;
+            let number = match side {
+                PieceColor::White => format!("{number}. "),
+                PieceColor::Black if need_number => format!("{number}... "),
+                PieceColor::Black => String::new(),
+            };
+            let turn = annotated.turn;
+            let text = match board.and_then(|board| board.path_for_with(turn, self.start.rules)) {
+                Some(path) => path.to_string(),
+                None => turn.to_string(),
+            };
+            // number is kept on the same line with the turn
+            line.push(f, &format!("{number}{text}"))?;
+            need_number = false;
+            if annotated.evaluation.is_some() || annotated.comment.is_some() {
+                let mut comment = String::from("{");
+                if let Some(evaluation) = annotated.evaluation {
+                    comment += &format!("[%eval {}]", evaluation_text(evaluation));
+                }
+                if let Some(text) = &annotated.comment {
+                    if comment.len() > 1 {
+                        comment.push(' ');
+                    }
+                    comment += text;
+                }
+                comment.push('}');
+                line.push(f, &comment)?;
+                need_number = true;
+            }
+
+            board = board.and_then(|board| {
+                board
+                    .try_perform_turn_with(turn, side, self.start.rules)
+                    .ok()
+            });
+            side = side.opposite();
+        }
+        line.push(f, result)?;
+        writeln!(f)
+    }
+}
+
+/// Parses and [replays](PgnGame::replay) the single game
+impl FromStr for PgnGame {
+    type Err = PgnError;
+
+    fn from_str(s: &str) -> Result<Self, PgnError> {
+        let mut parser = Parser { rest: s };
+        let game = parser.game()?;
+        if !parser.skip_whitespace().is_empty() {
+            return Err(PgnError::TrailingText);
+        }
+        Ok(game)
+    }
+}
+
+/// Parses and [replays](PgnGame::replay) all games of the text
+pub fn parse_games(s: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut parser = Parser { rest: s };
+    let mut games = Vec::new();
+    while !parser.skip_whitespace().is_empty() {
+        games.push(parser.game()?);
+    }
+    Ok(games)
+}
+
+/// `Date` tag value (`YYYY.MM.DD`, UTC) of `time`
+#[must_use]
+pub fn date(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    // days to the civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+fn write_tag(f: &mut std::fmt::Formatter<'_>, name: &str, value: &str) -> std::fmt::Result {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    writeln!(f, "[{name} \"{value}\"]")
+}
+
+/// Turns text wrapped at 80 characters
+#[derive(Default)]
+struct Line {
+    len: usize,
+}
+
+impl Line {
+    const MAX_LEN: usize = 80;
+
+    fn push(&mut self, f: &mut std::fmt::Formatter<'_>, token: &str) -> std::fmt::Result {
+        if self.len > 0 && self.len + 1 + token.len() > Self::MAX_LEN {
+            writeln!(f)?;
+            self.len = 0;
+        }
+        if self.len > 0 {
+            f.write_str(" ")?;
+            self.len += 1;
+        }
+        f.write_str(token)?;
+        self.len += token.len();
+        Ok(())
+    }
+}
+
+fn result_marker(result: Option<GameOutcome>) -> &'static str {
+    match result {
+        Some(GameOutcome::Win(PieceColor::White)) => "1-0",
+        Some(GameOutcome::Win(PieceColor::Black)) => "0-1",
+        Some(GameOutcome::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+fn parse_result_marker(marker: &str) -> Option<Option<GameOutcome>> {
+    match marker {
+        "1-0" => Some(Some(GameOutcome::Win(PieceColor::White))),
+        "0-1" => Some(Some(GameOutcome::Win(PieceColor::Black))),
+        "1/2-1/2" => Some(Some(GameOutcome::Draw)),
+        "*" => Some(None),
+        _ => None,
+    }
+}
+
+fn evaluation_text(evaluation: EvaluationResult) -> String {
+    match evaluation {
+        EvaluationResult::Victory { in_steps } => format!("#{in_steps}"),
+        EvaluationResult::Defeat { in_steps } => format!("-#{in_steps}"),
+        EvaluationResult::InBetween { score } => score.to_string(),
+        EvaluationResult::Draw => "draw".to_owned(),
+    }
+}
+
+fn parse_evaluation(text: &str) -> Result<EvaluationResult, PgnError> {
+    let evaluation = if text == "draw" {
+        Some(EvaluationResult::Draw)
+    } else if let Some(in_steps) = text.strip_prefix("-#") {
+        in_steps
+            .parse()
+            .ok()
+            .map(|in_steps| EvaluationResult::Defeat { in_steps })
+    } else if let Some(in_steps) = text.strip_prefix('#') {
+        in_steps
+            .parse()
+            .ok()
+            .map(|in_steps| EvaluationResult::Victory { in_steps })
+    } else {
+        text.parse()
+            .ok()
+            .map(|score| EvaluationResult::InBetween { score })
+    };
+    evaluation.ok_or(PgnError::InvalidEvaluation)
+}
+
+/// Splits `[%eval ...]` out of the comment
+fn parse_comment(text: &str) -> Result<(Option<EvaluationResult>, Option<String>), PgnError> {
+    let mut evaluation = None;
+    let mut text = text.to_owned();
+    if let Some(start) = text.find("[%eval") {
+        let len = text[start..].find(']').ok_or(PgnError::InvalidEvaluation)?;
+        evaluation = Some(parse_evaluation(
+            text[start + "[%eval".len()..start + len].trim(),
+        )?);
+        text.replace_range(start..=start + len, "");
+    }
+    let text = text.trim();
+    Ok((evaluation, (!text.is_empty()).then(|| text.to_owned())))
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) -> &'a str {
+        self.rest = self.rest.trim_start();
+        self.rest
+    }
+
+    fn tag(&mut self) -> Result<(String, String), PgnError> {
+        let rest = self.rest.strip_prefix('[').ok_or(PgnError::InvalidTag)?;
+        let name_len = rest
+            .find(|c: char| c.is_whitespace())
+            .ok_or(PgnError::InvalidTag)?;
+        let name = &rest[..name_len];
+        let quoted = rest[name_len..]
+            .trim_start()
+            .strip_prefix('"')
+            .ok_or(PgnError::InvalidTag)?;
+
+        let mut chars = quoted.char_indices();
+        let mut value = String::new();
+        let mut end = None;
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                '"' => {
+                    end = Some(i + 1);
+                    break;
+                }
+                '\n' => break,
+                _ => value.push(c),
+            }
+        }
+        let end = end.ok_or(PgnError::InvalidTag)?;
+        self.rest = quoted[end..]
+            .trim_start()
+            .strip_prefix(']')
+            .ok_or(PgnError::InvalidTag)?;
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(PgnError::InvalidTag);
+        }
+        Ok((name.to_owned(), value))
+    }
+
+    fn game(&mut self) -> Result<PgnGame, PgnError> {
+        let mut tags = Vec::new();
+        while self.skip_whitespace().starts_with('[') {
+            tags.push(self.tag()?);
+        }
+
+        let mut comment = None;
+        let mut turns = Vec::<AnnotatedTurn>::new();
+        let result = loop {
+            let rest = self.skip_whitespace();
+            if rest.is_empty() {
+                return Err(PgnError::MissingResult);
+            }
+            if let Some(body) = rest.strip_prefix('{') {
+                let len = body.find('}').ok_or(PgnError::UnclosedComment)?;
+                self.rest = &body[len + 1..];
+                let (target_comment, text) = match turns.last_mut() {
+                    Some(turn) => {
+                        let (evaluation, text) = parse_comment(&body[..len])?;
+                        if evaluation.is_some() {
+                            turn.evaluation = evaluation;
+                        }
+                        (&mut turn.comment, text)
+                    }
+                    // no turn to evaluate yet, the comment is kept as is
+                    None => {
+                        let text = body[..len].trim();
+                        (&mut comment, (!text.is_empty()).then(|| text.to_owned()))
+                    }
+                };
+                if let Some(text) = text {
+                    match target_comment {
+                        Some(old) => {
+                            old.push(' ');
+                            old.push_str(&text);
+                        }
+                        None => *target_comment = Some(text),
+                    }
+                }
+                continue;
+            }
+
+            let len = rest
+                .find(|c: char| c.is_whitespace() || c == '{')
+                .unwrap_or(rest.len());
+            let token = &rest[..len];
+            self.rest = &rest[len..];
+            if let Some(result) = parse_result_marker(token) {
+                break result;
+            }
+            // move number, possibly glued to the turn (`1.c3-d4`), digits without the dots
+            // are left to fail as a turn
+            let number_len = token
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(token.len());
+            let token = match token[number_len..].strip_prefix('.') {
+                Some(rest) if number_len > 0 => rest.trim_start_matches('.'),
+                _ => token,
+            };
+            if !token.is_empty() {
+                let turn = token.parse::<Turn>().map_err(PgnError::InvalidTurn)?;
+                turns.push(AnnotatedTurn::from(turn));
+            }
+        };
+
+        let variant = match tags.iter().find(|(name, _)| name == "Variant") {
+            Some((_, variant)) => {
+                let (board, rules) = match variant.split_once(' ') {
+                    Some((board, rules)) => (board, parse_rules(rules.trim())),
+                    None => (variant.as_str(), Some(RuleSet::CLASSIC)),
+                };
+                let config = BoardConfig::from_name(board).ok_or(PgnError::InvalidVariant)?;
+                Some((config, rules.ok_or(PgnError::InvalidVariant)?))
+            }
+            None => None,
+        };
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => {
+                let start = fen.parse::<Fen>().map_err(PgnError::InvalidFen)?;
+                if variant.is_some_and(|(config, rules)| {
+                    start.board.config() != config || start.rules != rules
+                }) {
+                    return Err(PgnError::VariantMismatch);
+                }
+                start
+            }
+            None => {
+                let (config, rules) = variant.unwrap_or((&BoardConfig::CLASSIC, RuleSet::CLASSIC));
+                Fen::initial(config, rules)
+            }
+        };
+        if let Some((_, tag)) = tags.iter().find(|(name, _)| name == "Result") {
+            if parse_result_marker(tag) != Some(result) {
+                return Err(PgnError::ResultMismatch);
+            }
+        }
+        tags.retain(|(name, _)| !matches!(name.as_str(), "Variant" | "FEN" | "Result"));
+
+        let game = PgnGame {
+            tags,
+            start,
+            comment,
+            turns,
+            result,
+        };
+        game.replay()?;
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Game of `count` turns taking the last generated turn each time
+    fn played_game(config: &'static BoardConfig, rules: RuleSet, count: usize) -> Game {
+        let mut game = Game::new(config, rules);
+        for _ in 0..count {
+            if game.is_finished() {
+                break;
+            }
+            let turn = game
+                .board()
+                .generate_all_turns_for_with(game.side_to_move(), rules)
+                .last()
+                .expect("player isn't blocked");
+            game.play(turn).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn annotated_game_round_trip() {
+        for (config, rules) in [
+            (&BoardConfig::CLASSIC, RuleSet::CLASSIC),
+            (&BoardConfig::LARGE, RuleSet::DIAGONAL),
+        ] {
+            let mut pgn = PgnGame::from_game(&played_game(config, rules, 40));
+            pgn.set_tag("Event", "test \"quoted\"");
+            pgn.comment = Some("[%eval 5] before the start".to_owned());
+            let evaluations = [
+                EvaluationResult::InBetween { score: -17 },
+                EvaluationResult::Victory { in_steps: 3 },
+                EvaluationResult::Defeat { in_steps: 8 },
+                EvaluationResult::Draw,
+            ];
+            for (i, turn) in pgn.turns.iter_mut().enumerate().step_by(3) {
+                turn.evaluation = Some(evaluations[i % evaluations.len()]);
+                if i % 2 == 0 {
+                    turn.comment = Some(format!("comment {i}"));
+                }
+            }
+            assert_eq!(pgn.to_string().parse(), Ok(pgn.clone()), "{pgn}");
+        }
+    }
+
+    #[test]
+    fn several_games_with_move_numbers_glued_to_turns() {
+        let text = "[Result \"*\"]\n\n1.c3-c4 f6-f5 *\n\n1. a3-a4 1... f6-e6 *\n";
+        let games = parse_games(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].turns.len(), 2);
+        assert_eq!(games[1].turns[1].turn, "f6-e6".parse().unwrap());
+    }
+
+    #[test]
+    fn invalid_games() {
+        assert_eq!(
+            "[Event test] *".parse::<PgnGame>(),
+            Err(PgnError::InvalidTag)
+        );
+        assert_eq!(
+            "[Variant \"7x7/9\"] *".parse::<PgnGame>(),
+            Err(PgnError::InvalidVariant)
+        );
+        assert_eq!(
+            "[Variant \"6x6/4\"] [FEN \"5BBB/5BBB/5BBB/8/8/WWW5/WWW5/WWW5 w 0\"] *"
+                .parse::<PgnGame>(),
+            Err(PgnError::VariantMismatch)
+        );
+        assert_eq!("1. c3-c4".parse::<PgnGame>(), Err(PgnError::MissingResult));
+        assert_eq!(
+            "1. c3-c4 {open *".parse::<PgnGame>(),
+            Err(PgnError::UnclosedComment)
+        );
+        assert_eq!(
+            "1. c3-c4 {[%eval #x]} *".parse::<PgnGame>(),
+            Err(PgnError::InvalidEvaluation)
+        );
+        assert_eq!(
+            "[Result \"1-0\"] 1. c3-c4 *".parse::<PgnGame>(),
+            Err(PgnError::ResultMismatch)
+        );
+        assert_eq!(
+            "1. c3-c4 f6-f5 2. c4-c6 *".parse::<PgnGame>(),
+            Err(PgnError::IllegalTurn {
+                turns_count: 2,
+                turn: "c4-c6".parse().unwrap(),
+            })
+        );
+        assert_eq!(
+            "1. c3-c4 * *".parse::<PgnGame>(),
+            Err(PgnError::TrailingText)
+        );
+        // stray number isn't taken for the move number
+        assert!(matches!(
+            "1. c3-c4 42 f6-f5 *".parse::<PgnGame>(),
+            Err(PgnError::InvalidTurn(_))
+        ));
+    }
+
+    #[test]
+    fn illegal_turns_are_written() {
+        let mut pgn = PgnGame::from_game(&Game::new(&BoardConfig::CLASSIC, RuleSet::CLASSIC));
+        for turn in ["a1-a8", "a3:c3:c5", "f6-f5"] {
+            pgn.turns
+                .push(AnnotatedTurn::from(turn.parse::<Turn>().unwrap()));
+        }
+        let text = pgn.to_string();
+        assert!(text.contains("1. a1-a8 a3-c5 2. f6-f5 *"), "{text}");
+    }
+}
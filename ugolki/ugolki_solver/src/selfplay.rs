@@ -0,0 +1,457 @@
+//! Matches between two searcher configurations with Elo estimation and SPRT
+//!
+//! Each starting position is played twice, with the players swapping colours. Games are
+//! adjudicated by [`BoardPosition::winner_with`], game reaching the turns limit without
+//! a result counts as a draw.
+
+use crate::fen::Fen;
+use crate::game::Game;
+use crate::pgn::{AnnotatedTurn, PgnGame};
+use crate::{BoardConfig, BoardPosition, GameOutcome, PieceColor, RuleSet, Turn, TurnSearcher};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// How long player searches for each turn
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SearchLimit {
+    Depth(usize),
+    Time(Duration),
+}
+
+impl std::fmt::Display for SearchLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Depth(depth) => write!(f, "depth {depth}"),
+            Self::Time(budget) => write!(f, "{}ms", budget.as_millis()),
+        }
+    }
+}
+
+/// Searcher configuration taking part in the match
+#[derive(Debug, Clone)]
+pub struct Player {
+    pub searcher: TurnSearcher,
+    pub limit: SearchLimit,
+}
+
+impl Player {
+    #[must_use]
+    #[inline]
+    pub fn new(searcher: TurnSearcher, limit: SearchLimit) -> Self {
+        Self { searcher, limit }
+    }
+
+    #[inline]
+    fn next_turn(
+        &mut self,
+        board: BoardPosition,
+        color: PieceColor,
+        turns_count: usize,
+    ) -> Option<Turn> {
+        match self.limit {
+            SearchLimit::Depth(depth) => self.searcher.next_turn(board, color, depth, turns_count),
+            SearchLimit::Time(budget) => {
+                self.searcher
+                    .next_turn_timed(board, color, budget, turns_count)
+            }
+        }
+        .best_turn()
+    }
+}
+
+/// Position the game is started from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StartPosition {
+    pub board: BoardPosition,
+    pub side_to_move: PieceColor,
+    pub turns_count: usize,
+    /// Turns leading to the position from the initial one (empty if unknown)
+    pub opening: Vec<Turn>,
+}
+
+impl StartPosition {
+    #[must_use]
+    #[inline]
+    pub fn initial(config: &'static BoardConfig) -> Self {
+        Self {
+            board: BoardPosition::initial(config),
+            side_to_move: PieceColor::White,
+            turns_count: 0,
+            opening: Vec::new(),
+        }
+    }
+
+    /// All distinct positions after `plies` turns under `rules` from the initial position
+    /// of `config` board
+    #[must_use]
+    pub fn openings(config: &'static BoardConfig, rules: RuleSet, plies: usize) -> Vec<Self> {
+        let mut positions = vec![Self::initial(config)];
+        for _ in 0..plies {
+            let mut next = BTreeMap::new();
+            for start in positions {
+                for turn in start
+                    .board
+                    .generate_all_turns_for_with(start.side_to_move, rules)
+                {
+                    let board = start.board.perform_turn(turn, start.side_to_move);
+                    next.entry(board).or_insert_with(|| {
+                        let mut opening = start.opening.clone();
+                        opening.push(turn);
+                        Self {
+                            board,
+                            side_to_move: start.side_to_move.opposite(),
+                            turns_count: start.turns_count + 1,
+                            opening,
+                        }
+                    });
+                }
+            }
+            positions = next.into_values().collect();
+        }
+        positions
+    }
+}
+
+/// Finished game
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    pub start: StartPosition,
+    pub turns: Vec<Turn>,
+    pub outcome: GameOutcome,
+}
+
+impl GameRecord {
+    /// Record of the game played under `rules` without tags, starts from the initial
+    /// position if the opening is known
+    #[must_use]
+    pub fn pgn(&self, rules: RuleSet) -> PgnGame {
+        let start = &self.start;
+        let (fen, turns) = if start.opening.len() == start.turns_count {
+            (
+                Fen::initial(start.board.config(), rules),
+                [start.opening.as_slice(), &self.turns].concat(),
+            )
+        } else {
+            let fen = Fen {
+                board: start.board,
+                side_to_move: start.side_to_move,
+                turns_count: start.turns_count,
+                rules,
+            };
+            (fen, self.turns.clone())
+        };
+        PgnGame {
+            tags: Vec::new(),
+            start: fen,
+            comment: None,
+            turns: turns.into_iter().map(AnnotatedTurn::from).collect(),
+            result: Some(self.outcome),
+        }
+    }
+}
+
+/// Plays single game, player without turns loses
+///
+/// Panics if the searchers use different rules.
+pub fn play_game(
+    white: &mut Player,
+    black: &mut Player,
+    start: &StartPosition,
+    max_turns: usize,
+) -> GameRecord {
+    assert_eq!(
+        white.searcher.rules(),
+        black.searcher.rules(),
+        "players should use the same rules"
+    );
+    let mut game = Game::from_position(
+        start.board,
+        start.side_to_move,
+        start.turns_count,
+        white.searcher.rules(),
+    );
+
+    let outcome = loop {
+        if let Some(outcome) = game.winner() {
+            break outcome;
+        }
+        if game.turns_count() >= max_turns {
+            break GameOutcome::Draw;
+        }
+
+        let side = game.side_to_move();
+        let player = match side {
+            PieceColor::White => &mut *white,
+            PieceColor::Black => &mut *black,
+        };
+        let Some(turn) = player.next_turn(game.board(), side, game.turns_count()) else {
+            break GameOutcome::Win(side.opposite());
+        };
+        game.play(turn).expect("searched turn is legal");
+    };
+
+    GameRecord {
+        start: start.clone(),
+        turns: game.turns().to_vec(),
+        outcome,
+    }
+}
+
+/// Sequential probability ratio test of "first player is `elo1` stronger" against "`elo0` stronger"
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// Probability of accepting `elo1` when `elo0` is true
+    pub alpha: f64,
+    /// Probability of accepting `elo0` when `elo1` is true
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.,
+            elo1: 10.,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// Log likelihood ratio below which `elo0` is accepted
+    #[must_use]
+    #[inline]
+    pub fn lower_bound(&self) -> f64 {
+        (self.beta / (1. - self.alpha)).ln()
+    }
+
+    /// Log likelihood ratio above which `elo1` is accepted
+    #[must_use]
+    #[inline]
+    pub fn upper_bound(&self) -> f64 {
+        ((1. - self.beta) / self.alpha).ln()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SprtVerdict {
+    /// `elo0` hypothesis accepted
+    H0,
+    /// `elo1` hypothesis accepted
+    H1,
+    Continue,
+}
+
+#[inline]
+fn elo_to_score(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+#[inline]
+fn score_to_elo(score: f64) -> f64 {
+    -400. * (1. / score - 1.).log10()
+}
+
+/// Match results from the first player's point of view
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchResult {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl MatchResult {
+    #[must_use]
+    #[inline]
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Average points per game (draw is half a point)
+    #[must_use]
+    #[inline]
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.) / self.games().max(1) as f64
+    }
+
+    /// Variance of points of a single game
+    #[inline]
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        (self.wins as f64 * (1. - score).powi(2)
+            + self.losses as f64 * score.powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2))
+            / self.games().max(1) as f64
+    }
+
+    /// Elo difference with half width of its 95% confidence interval
+    ///
+    /// `None` if no games were played or one player got all the points (the difference
+    /// is unbounded then). Interval is infinite when its end reaches such score.
+    #[must_use]
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let score = self.score();
+        if self.games() == 0 || score == 0. || score == 1. {
+            return None;
+        }
+        let deviation = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let low = score_to_elo((score - deviation).max(0.));
+        let high = score_to_elo((score + deviation).min(1.));
+        Some((score_to_elo(score), (high - low) / 2.))
+    }
+
+    /// Log likelihood ratio of `sprt` hypotheses (normal approximation)
+    ///
+    /// Games with equal results (e.g. all drawn) have no variance, then it's estimated as if
+    /// one more win and one more loss were played, so the test still converges.
+    #[must_use]
+    pub fn llr(&self, sprt: &Sprt) -> f64 {
+        if self.games() == 0 {
+            return 0.;
+        }
+        let mut variance = self.variance();
+        if variance == 0. {
+            variance = Self {
+                wins: self.wins + 1,
+                losses: self.losses + 1,
+                draws: self.draws,
+            }
+            .variance();
+        }
+        let (score0, score1) = (elo_to_score(sprt.elo0), elo_to_score(sprt.elo1));
+        self.games() as f64 * (score1 - score0) * (2. * self.score() - score0 - score1)
+            / (2. * variance)
+    }
+
+    #[must_use]
+    pub fn sprt(&self, sprt: &Sprt) -> SprtVerdict {
+        let llr = self.llr(sprt);
+        if llr >= sprt.upper_bound() {
+            SprtVerdict::H1
+        } else if llr <= sprt.lower_bound() {
+            SprtVerdict::H0
+        } else {
+            SprtVerdict::Continue
+        }
+    }
+
+    #[inline]
+    fn add(&mut self, outcome: GameOutcome, first_color: PieceColor) {
+        match outcome {
+            GameOutcome::Win(winner) if winner == first_color => self.wins += 1,
+            GameOutcome::Win(_) => self.losses += 1,
+            GameOutcome::Draw => self.draws += 1,
+        }
+    }
+}
+
+/// Match settings
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Match {
+    pub starts: Vec<StartPosition>,
+    /// Games reaching this turns count are drawn
+    pub max_turns: usize,
+    /// Stop as soon as the test gives a verdict
+    pub sprt: Option<Sprt>,
+}
+
+impl Match {
+    /// Plays game pairs from each start position, `on_game` is called after each game
+    ///
+    /// Searchers' tables are cleared before each game. Panics if the players use different rules.
+    pub fn run(
+        &self,
+        first: &mut Player,
+        second: &mut Player,
+        mut on_game: impl FnMut(&GameRecord, &MatchResult),
+    ) -> MatchResult {
+        let mut result = MatchResult::default();
+        for start in &self.starts {
+            for first_color in [PieceColor::White, PieceColor::Black] {
+                first.searcher.clear_table();
+                second.searcher.clear_table();
+                let record = match first_color {
+                    PieceColor::White => play_game(first, second, start, self.max_turns),
+                    PieceColor::Black => play_game(second, first, start, self.max_turns),
+                };
+                result.add(record.outcome, first_color);
+                on_game(&record, &result);
+            }
+
+            if let Some(sprt) = &self.sprt {
+                if result.sprt(sprt) != SprtVerdict::Continue {
+                    break;
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_is_unbounded_without_points_of_one_player() {
+        let all_wins = MatchResult {
+            wins: 10,
+            ..Default::default()
+        };
+        assert_eq!(all_wins.elo(), None);
+        assert_eq!(MatchResult::default().elo(), None);
+
+        let even = MatchResult {
+            wins: 5,
+            losses: 5,
+            draws: 10,
+        };
+        let (elo, error) = even.elo().unwrap();
+        assert!(elo.abs() < 1e-9);
+        assert!(error.is_finite() && error > 0.);
+    }
+
+    #[test]
+    fn sprt_stops_on_equal_results() {
+        let sprt = Sprt::default();
+        let draws = |draws| MatchResult {
+            draws,
+            ..Default::default()
+        };
+        assert!(draws(1).llr(&sprt) < 0.);
+        assert_eq!(draws(1000).sprt(&sprt), SprtVerdict::H0);
+
+        let wins = MatchResult {
+            wins: 1000,
+            ..Default::default()
+        };
+        assert_eq!(wins.sprt(&sprt), SprtVerdict::H1);
+    }
+
+    #[test]
+    #[should_panic(expected = "same rules")]
+    fn players_with_different_rules_are_rejected() {
+        let mut white = Player::new(TurnSearcher::new(), SearchLimit::Depth(1));
+        let mut searcher = TurnSearcher::new();
+        searcher.set_rules(RuleSet {
+            diagonal: true,
+            ..RuleSet::CLASSIC
+        });
+        let mut black = Player::new(searcher, SearchLimit::Depth(1));
+        let _ = play_game(
+            &mut white,
+            &mut black,
+            &StartPosition::initial(&BoardConfig::SMALL),
+            10,
+        );
+    }
+}
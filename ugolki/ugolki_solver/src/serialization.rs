@@ -0,0 +1,367 @@
+//! `Serialize` / `Deserialize` implementations (`serde` feature)
+//!
+//! Boards, turns and positions are written as text in human readable formats (like JSON)
+//! and compactly in binary ones:
+//!
+//! | type | readable | binary |
+//! |---|---|---|
+//! | [`Position`] | [notation](crate::notation) `c3` | `u8` index |
+//! | [`PositionMask`] | list of squares | `u128` |
+//! | [`Turn`], [`TurnPath`] | notation `c3-c4`, `c3:e3:e5` | squares |
+//! | [`BoardConfig`] | name `8x8/9` | width, height, camp width and height |
+//! | [`BoardPosition`] | rows of [`fen`](crate::fen) | board and masks of both colors |
+//! | [`Fen`] | [`fen`](crate::fen) string | board, side, turns count and rules |
+//!
+//! [`Game`] is written as the start, all turns (including the undone ones) and the number of
+//! played turns, and is replayed when read. Other plain data types derive both traits.
+//! Searchers, tables and iterators aren't serialised, tablebase and opening book have
+//! their own file formats.
+
+use crate::fen::{parse_rows, write_rows, Fen};
+use crate::game::Game;
+use crate::notation::path_from_positions;
+use crate::path::TurnPath;
+use crate::{BoardConfig, BoardPosition, PieceColor, Position, PositionMask, RuleSet, Turn};
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+
+/// Parses readable string with [`FromStr`]
+fn deserialize_str<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<T, D::Error>
+where
+    T::Err: std::fmt::Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(D::Error::custom)
+}
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u8(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            return deserialize_str(deserializer);
+        }
+        let index = u8::deserialize(deserializer)?;
+        if index as usize >= Position::COUNT {
+            return Err(D::Error::custom("position is out of range"));
+        }
+        Ok(Position(index))
+    }
+}
+
+impl Serialize for PositionMask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_u128(self.0);
+        }
+        let mut seq = serializer.serialize_seq(Some(self.count() as usize))?;
+        for pos in self.positions_iter() {
+            seq.serialize_element(&pos)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PositionMask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            let mask = PositionMask(u128::deserialize(deserializer)?);
+            if mask - PositionMask::FULL != PositionMask::EMPTY {
+                return Err(D::Error::custom("position is out of range"));
+            }
+            return Ok(mask);
+        }
+
+        struct MaskVisitor;
+
+        impl<'de> Visitor<'de> for MaskVisitor {
+            type Value = PositionMask;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("list of squares")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PositionMask, A::Error> {
+                let mut mask = PositionMask::EMPTY;
+                while let Some(pos) = seq.next_element::<Position>()? {
+                    mask.add_position(pos);
+                }
+                Ok(mask)
+            }
+        }
+
+        deserializer.deserialize_seq(MaskVisitor)
+    }
+}
+
+impl Serialize for Turn {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            (self.from, self.to).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Turn {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            return deserialize_str(deserializer);
+        }
+        let (from, to) = Deserialize::deserialize(deserializer)?;
+        Ok(Turn { from, to })
+    }
+}
+
+impl Serialize for TurnPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            self.positions().serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for TurnPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            return deserialize_str(deserializer);
+        }
+        path_from_positions(Vec::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for BoardConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            (self.width, self.height, self.camp_width, self.camp_height).serialize(serializer)
+        }
+    }
+}
+
+/// Only [`BoardConfig::VARIANTS`] can be read
+impl<'de> Deserialize<'de> for &'static BoardConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let config = if deserializer.is_human_readable() {
+            BoardConfig::from_name(&String::deserialize(deserializer)?)
+        } else {
+            let (width, height, camp_width, camp_height) = Deserialize::deserialize(deserializer)?;
+            BoardConfig::find(width, height, camp_width, camp_height)
+        };
+        config.ok_or_else(|| D::Error::custom("unknown board"))
+    }
+}
+
+/// Rows written by [`fen`](crate::fen), for use in readable formats
+struct Rows(BoardPosition);
+
+impl std::fmt::Display for Rows {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_rows(f, self.0)
+    }
+}
+
+impl Serialize for BoardPosition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&Rows(*self))
+        } else {
+            (self.1, self.0).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BoardPosition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let rows = String::deserialize(deserializer)?;
+            return parse_rows(&rows).map_err(D::Error::custom);
+        }
+        let (config, pieces) = Deserialize::deserialize(deserializer)?;
+        let board = BoardPosition(pieces, config);
+        if !board.is_valid_position() {
+            return Err(D::Error::custom("invalid position"));
+        }
+        Ok(board)
+    }
+}
+
+impl Serialize for Fen {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            (
+                self.board,
+                self.side_to_move,
+                self.turns_count as u64,
+                self.rules,
+            )
+                .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Fen {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            return deserialize_str(deserializer);
+        }
+        let (board, side_to_move, turns_count, rules): (_, PieceColor, u64, RuleSet) =
+            Deserialize::deserialize(deserializer)?;
+        Ok(Fen {
+            board,
+            side_to_move,
+            turns_count: turns_count as usize,
+            rules,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GameData {
+    start: Fen,
+    /// Played turns followed by the undone ones
+    turns: Vec<Turn>,
+    /// Number of played turns
+    played: usize,
+}
+
+impl Serialize for Game {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GameData {
+            start: Fen {
+                board: self.start(),
+                side_to_move: self.start_side(),
+                turns_count: self.start_turns_count(),
+                rules: self.rules(),
+            },
+            turns: [self.turns(), self.undone_turns()].concat(),
+            played: self.turns().len(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Replays the turns, so illegal ones are errors
+impl<'de> Deserialize<'de> for Game {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = GameData::deserialize(deserializer)?;
+        if data.played > data.turns.len() {
+            return Err(D::Error::custom("more played turns than turns"));
+        }
+        let mut game = Game::from_fen(data.start);
+        for turn in &data.turns {
+            game.play(*turn)
+                .map_err(|err| D::Error::custom(format_args!("turn {turn}: {err}")))?;
+        }
+        for _ in data.played..data.turns.len() {
+            game.undo();
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EvaluationResult, GameOutcome};
+    use serde::de::DeserializeOwned;
+    use std::fmt::Debug;
+
+    /// Checks both readable (JSON) and binary (postcard) round trips, returns the JSON
+    fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) -> String {
+        let json = serde_json::to_string(value).unwrap();
+        assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value, "{json}");
+        let bytes = postcard::to_allocvec(value).unwrap();
+        assert_eq!(&postcard::from_bytes::<T>(&bytes).unwrap(), value, "{json}");
+        json
+    }
+
+    #[test]
+    fn readable_forms() {
+        let initial = BoardPosition::initial(&BoardConfig::CLASSIC);
+        assert_eq!(round_trip(&Position::from_xy(2, 2)), r#""c3""#);
+        assert_eq!(
+            round_trip(&PositionMask::from_position(Position::from_xy(0, 1))),
+            r#"["a2"]"#
+        );
+        assert_eq!(
+            round_trip(&"c3:e3:e5".parse::<TurnPath>().unwrap()),
+            r#""c3:e3:e5""#
+        );
+        assert_eq!(
+            round_trip(&initial),
+            r#""5BBB/5BBB/5BBB/8/8/WWW5/WWW5/WWW5""#
+        );
+        assert_eq!(
+            round_trip(&Fen::initial(&BoardConfig::SMALL, RuleSet::DIAGONAL)),
+            r#""4BB/4BB/6/6/WW4/WW4 w 0 diagonal""#
+        );
+        round_trip(&RuleSet::OPPONENT_JUMPS);
+        round_trip(&EvaluationResult::Victory { in_steps: 3 });
+        round_trip(&GameOutcome::Win(PieceColor::Black));
+
+        let config: &'static BoardConfig = serde_json::from_str(r#""10x10/16""#).unwrap();
+        assert_eq!(config, &BoardConfig::LARGE);
+        let bytes = postcard::to_allocvec(&BoardConfig::SMALL).unwrap();
+        let config: &'static BoardConfig = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(config, &BoardConfig::SMALL);
+    }
+
+    #[test]
+    fn game_keeps_undone_turns() {
+        let mut game = Game::new(&BoardConfig::CLASSIC, RuleSet::DIAGONAL);
+        for i in 0..30 {
+            let turns = game
+                .board()
+                .generate_all_turns_for_with(game.side_to_move(), game.rules())
+                .collect::<Vec<_>>();
+            let turn = turns[i * 7 % turns.len()];
+            for path in game.board().paths_for_with(turn, game.rules()) {
+                round_trip(&path);
+            }
+            round_trip(&turn);
+            round_trip(&game.fen());
+            game.play(turn).unwrap();
+        }
+        game.undo();
+        game.undo();
+        round_trip(&game);
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert!(serde_json::from_str::<Position>(r#""z9""#).is_err());
+        assert!(serde_json::from_str::<TurnPath>(r#""c3:c4""#).is_err());
+        assert!(serde_json::from_str::<&'static BoardConfig>(r#""7x7/9""#).is_err());
+        assert!(
+            serde_json::from_str::<BoardPosition>(r#""5BBB/5BBB/5BB1/8/8/WWW5/WWW5/WWW5""#)
+                .is_err()
+        );
+        let game =
+            r#"{"start":"5BBB/5BBB/5BBB/8/8/WWW5/WWW5/WWW5 w 0","turns":["c3-c5"],"played":1}"#;
+        assert!(serde_json::from_str::<Game>(game).is_err());
+        let game = r#"{"start":"5BBB/5BBB/5BBB/8/8/WWW5/WWW5/WWW5 w 0","turns":[],"played":1}"#;
+        assert!(serde_json::from_str::<Game>(game).is_err());
+
+        let bytes = postcard::to_allocvec(&(Position::COUNT as u8)).unwrap();
+        assert!(postcard::from_bytes::<Position>(&bytes).is_err());
+        let bytes = postcard::to_allocvec(&u128::MAX).unwrap();
+        assert!(postcard::from_bytes::<PositionMask>(&bytes).is_err());
+    }
+}
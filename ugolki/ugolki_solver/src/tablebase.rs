@@ -0,0 +1,500 @@
+//! Endgame tablebase for positions where almost all pieces reached their target camps
+//!
+//! Covers every position where each side has at most `max_outside` pieces outside of its
+//! target camp. Positions are solved by retrograde analysis: starting from the finished
+//! games (according to [`BoardPosition::winner_with`]) results are propagated back to the
+//! positions leading to them, so each solved position gets the distance to the end.
+//!
+//! The game is assumed to be late enough for the anti-stalling rule ([`StallingPolicy`]) to
+//! be in effect. Turns leading to positions which aren't covered (a piece leaves the camp
+//! when there are already `max_outside` pieces outside) aren't solved: side having such
+//! a turn is never considered lost, and victories only use the covered turns (so their
+//! distances are upper bounds if leaving the tablebase could win faster). Defeats are
+//! proven only when the losing side can't leave the covered positions, so tablebases with
+//! larger `max_outside` solve a much larger share of their positions.
+//!
+//! Positions where neither side can force the win stay unsolved, finished games aren't
+//! stored either (see [`BoardPosition::winner_with`] for them).
+//!
+//! Number of indices grows fast with `max_outside`: on the classic board 492 032 for 1
+//! (352 064 of them are positions, the rest have overlapping pieces), 5 822 499 872
+//! for 2.
+//!
+//! File format (all integers are little endian):
+//!
+//! | bytes | content                                      |
+//! |-------|----------------------------------------------|
+//! | 4     | magic `UGTB`                                 |
+//! | 1     | format version (4)                           |
+//! | 1     | `max_outside`                                |
+//! | 4     | board width, height, camp width and height   |
+//! | 1     | move rules ([`RuleSet::to_bits`])            |
+//! | 3     | stalling ([`StallingPolicy::to_bytes`])      |
+//! | 2     | reserved (0)                                 |
+//! | 8     | number of indices                            |
+//! | n     | one byte per index                           |
+//!
+//! Byte is `0` for unsolved or finished position and unused index, `1..=127` for victory of the side
+//! to move in that many plies and `128 + n` for defeat in `n` plies.
+//!
+//! Positions are numbered by [`CampIndexer`], so the positions with the same numbers of
+//! pieces outside of the camps form contiguous slices of the file.
+
+use crate::indexing::CampIndexer;
+use crate::{
+    BoardConfig, BoardPosition, EvaluationResult, GameOutcome, PieceColor, RuleSet, StallingPolicy,
+};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"UGTB";
+const VERSION: u8 = 4;
+const HEADER_LEN: usize = 24;
+
+const MAX_WIN_PLIES: u8 = 127;
+const LOSS_BASE: u8 = 128;
+
+/// Turns count with anti-stalling rule in effect and `color` to move
+#[inline]
+const fn late_game_turns(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => usize::MAX - 1,
+        PieceColor::Black => usize::MAX,
+    }
+}
+
+/// Indices of the positions after each turn, `None` for positions which aren't covered
+#[inline]
+fn children(
+    indexer: &CampIndexer,
+    board: BoardPosition,
+    color: PieceColor,
+    rules: RuleSet,
+) -> impl Iterator<Item = Option<usize>> + '_ {
+    board
+        .generate_all_turns_for_with(color, rules)
+        .map(move |turn| {
+            indexer
+                .index(board.perform_turn(turn, color), color.opposite())
+                .map(|child| child as usize)
+        })
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum State {
+    Unknown,
+    /// Side to move wins in that many plies (0 if it has already won)
+    Win(u8),
+    /// Side to move loses in that many plies (0 if it has already lost)
+    Loss(u8),
+    /// Finished in a draw
+    Draw,
+}
+
+/// Solved late game positions, see [module docs](self)
+#[derive(Clone)]
+pub struct Tablebase {
+    rules: RuleSet,
+    max_outside: usize,
+    indexer: CampIndexer,
+    values: Vec<u8>,
+}
+
+impl Tablebase {
+    /// Solves all positions of the `config` board with at most `max_outside` pieces outside
+    /// of the target camp for each side, the game follows `rules`
+    ///
+    /// `None` if there are too many positions to link them with `u32` indices.
+    #[must_use]
+    pub fn generate(
+        config: &'static BoardConfig,
+        rules: RuleSet,
+        max_outside: usize,
+    ) -> Option<Self> {
+        let indexer = CampIndexer::new(config, max_outside);
+        if indexer.len() >= u32::MAX as u64 {
+            return None;
+        }
+        let len = indexer.len() as usize;
+        let mut states = vec![State::Unknown; len];
+        // turns not known to lose, including the ones leaving the tablebase
+        let mut remaining_turns = vec![0u16; len];
+        let mut queue = VecDeque::new();
+
+        // positions leading to each position, stored as compressed rows
+        let mut parents_start = vec![0u32; len + 1];
+
+        for i in 0..len {
+            let Some((board, color)) = indexer.position(i as u64) else {
+                continue;
+            };
+            match board.winner_with(late_game_turns(color), rules) {
+                Some(GameOutcome::Win(winner)) => {
+                    states[i] = if winner == color {
+                        State::Win(0)
+                    } else {
+                        State::Loss(0)
+                    };
+                    queue.push_back(i);
+                }
+                Some(GameOutcome::Draw) => states[i] = State::Draw,
+                None => {
+                    for child in children(&indexer, board, color, rules) {
+                        if let Some(child) = child {
+                            parents_start[child + 1] += 1;
+                        }
+                        remaining_turns[i] += 1;
+                    }
+                }
+            }
+        }
+        for i in 0..len {
+            parents_start[i + 1] += parents_start[i];
+        }
+        let mut parents = vec![0u32; parents_start[len] as usize];
+        let mut filled = parents_start.clone();
+        for (i, state) in states.iter().enumerate() {
+            if *state != State::Unknown {
+                continue;
+            }
+            let Some((board, color)) = indexer.position(i as u64) else {
+                continue;
+            };
+            for child in children(&indexer, board, color, rules).flatten() {
+                parents[filled[child] as usize] = i as u32;
+                filled[child] += 1;
+            }
+        }
+
+        // queue holds positions in order of the distance, so the first found victory
+        // is the fastest one and the last refuted turn gives the longest defeat
+        while let Some(i) = queue.pop_front() {
+            let state = states[i];
+            for parent in &parents[parents_start[i] as usize..parents_start[i + 1] as usize] {
+                let parent = *parent as usize;
+                if states[parent] != State::Unknown {
+                    continue;
+                }
+                match state {
+                    State::Loss(plies) if plies < MAX_WIN_PLIES => {
+                        states[parent] = State::Win(plies + 1);
+                        queue.push_back(parent);
+                    }
+                    State::Win(plies) if plies < MAX_WIN_PLIES => {
+                        remaining_turns[parent] -= 1;
+                        if remaining_turns[parent] == 0 {
+                            states[parent] = State::Loss(plies + 1);
+                            queue.push_back(parent);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let values = states
+            .into_iter()
+            .map(|state| match state {
+                State::Unknown | State::Draw | State::Win(0) | State::Loss(0) => 0,
+                State::Win(plies) => plies,
+                State::Loss(plies) => LOSS_BASE + plies,
+            })
+            .collect();
+
+        Some(Self {
+            rules,
+            max_outside,
+            indexer,
+            values,
+        })
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn config(&self) -> &'static BoardConfig {
+        self.indexer.config()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn max_outside(&self) -> usize {
+        self.max_outside
+    }
+
+    /// Number of indices (positions solved or not and the unused ones)
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Number of solved positions
+    #[must_use]
+    pub fn solved_count(&self) -> usize {
+        self.values.iter().filter(|value| **value != 0).count()
+    }
+
+    /// Exact evaluation for `side_to_move` in search terms
+    ///
+    /// `None` if position isn't covered (including positions of other boards), isn't solved
+    /// or it's too early in the game.
+    #[must_use]
+    #[inline]
+    pub fn probe(
+        &self,
+        board: BoardPosition,
+        side_to_move: PieceColor,
+        turns_count: usize,
+    ) -> Option<EvaluationResult> {
+        if self
+            .rules
+            .stalling
+            .limit_plies()
+            .is_some_and(|limit| turns_count <= limit)
+        {
+            return None;
+        }
+        let value = self.values[self.indexer.index(board, side_to_move)? as usize];
+
+        // turn finishing the game has evaluation in 0 steps
+        match value {
+            0 => None,
+            1..=MAX_WIN_PLIES => Some(EvaluationResult::Victory {
+                in_steps: value as i32 - 1,
+            }),
+            _ => Some(EvaluationResult::Defeat {
+                in_steps: (value - LOSS_BASE - 1) as i32,
+            }),
+        }
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4] = VERSION;
+        header[5] = self.max_outside as u8;
+        let config = self.indexer.config();
+        header[6..11].copy_from_slice(&[
+            config.width,
+            config.height,
+            config.camp_width,
+            config.camp_height,
+            self.rules.to_bits(),
+        ]);
+        header[11..14].copy_from_slice(&self.rules.stalling.to_bytes());
+        header[16..].copy_from_slice(&(self.values.len() as u64).to_le_bytes());
+
+        writer.write_all(&header)?;
+        writer.write_all(&self.values)
+    }
+
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a tablebase file"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid("unsupported tablebase version"));
+        }
+
+        let max_outside = header[5] as usize;
+        let config = BoardConfig::find(header[6], header[7], header[8], header[9])
+            .ok_or_else(|| invalid("unknown board"))?;
+        let rules = RuleSet {
+            stalling: StallingPolicy::from_bytes(header[11..14].try_into().unwrap())
+                .ok_or_else(|| invalid("unknown stalling policy"))?,
+            ..RuleSet::from_bits(header[10]).ok_or_else(|| invalid("unknown rules"))?
+        };
+        let indexer = CampIndexer::new(config, max_outside);
+        let len = u64::from_le_bytes(header[16..].try_into().unwrap());
+        if len != indexer.len() {
+            return Err(invalid("tablebase size doesn't match its header"));
+        }
+
+        let mut values = vec![0; indexer.len() as usize];
+        reader.read_exact(&mut values)?;
+
+        Ok(Self {
+            rules,
+            max_outside,
+            indexer,
+            values,
+        })
+    }
+}
+
+impl std::fmt::Debug for Tablebase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tablebase")
+            .field("config", &format_args!("{}", self.indexer.config()))
+            .field("rules", &self.rules)
+            .field("max_outside", &self.max_outside)
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluation of the finished game or the probed one
+    fn evaluation(
+        tablebase: &Tablebase,
+        board: BoardPosition,
+        color: PieceColor,
+    ) -> Option<EvaluationResult> {
+        let turns_count = late_game_turns(color);
+        match board.winner_with(turns_count, tablebase.rules()) {
+            Some(GameOutcome::Win(winner)) if winner == color => {
+                Some(EvaluationResult::Victory { in_steps: -1 })
+            }
+            Some(GameOutcome::Win(_)) => Some(EvaluationResult::Defeat { in_steps: -1 }),
+            Some(GameOutcome::Draw) => Some(EvaluationResult::Draw),
+            None => tablebase.probe(board, color, turns_count),
+        }
+    }
+
+    #[test]
+    fn verdicts_follow_the_turns() {
+        for rules in [RuleSet::CLASSIC, RuleSet::DIAGONAL] {
+            let tablebase = Tablebase::generate(&BoardConfig::SMALL, rules, 1).unwrap();
+            assert!(tablebase.solved_count() > 0);
+            for index in 0..tablebase.len() as u64 {
+                let Some((board, color)) = tablebase.indexer.position(index) else {
+                    continue;
+                };
+                let Some(verdict) = tablebase.probe(board, color, late_game_turns(color)) else {
+                    continue;
+                };
+                let mut children = board.generate_all_turns_for_with(color, rules).map(|turn| {
+                    evaluation(
+                        &tablebase,
+                        board.perform_turn(turn, color),
+                        color.opposite(),
+                    )
+                });
+                match verdict {
+                    // some turn leaves the opponent lost in one step less
+                    EvaluationResult::Victory { in_steps } => assert!(
+                        children.any(|child| child
+                            == Some(EvaluationResult::Defeat {
+                                in_steps: in_steps - 1
+                            })),
+                        "{board:?} {color}"
+                    ),
+                    // including the turns leaving the tablebase
+                    EvaluationResult::Defeat { in_steps } => assert!(
+                        children.all(|child| matches!(
+                            child,
+                            Some(EvaluationResult::Victory { in_steps: steps }) if steps < in_steps
+                        )),
+                        "{board:?} {color}"
+                    ),
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn documented_index_counts() {
+        assert_eq!(CampIndexer::new(&BoardConfig::CLASSIC, 1).len(), 492_032);
+        assert_eq!(
+            CampIndexer::new(&BoardConfig::CLASSIC, 2).len(),
+            5_822_499_872
+        );
+        // more than u64 can count
+        assert_eq!(CampIndexer::new(&BoardConfig::LARGE, 16).len(), u64::MAX);
+    }
+
+    #[test]
+    fn oversized_tables_are_refused() {
+        for (config, max_outside) in [(&BoardConfig::CLASSIC, 2), (&BoardConfig::LARGE, 16)] {
+            assert!(Tablebase::generate(config, RuleSet::CLASSIC, max_outside).is_none());
+        }
+    }
+
+    #[test]
+    fn header_fields() {
+        let indexer = CampIndexer::new(&BoardConfig::SMALL, 1);
+        let rules = RuleSet {
+            stalling: StallingPolicy::UNLIMITED,
+            ..RuleSet::OPPONENT_JUMPS
+        };
+        let tablebase = Tablebase {
+            rules,
+            max_outside: 1,
+            values: (0..indexer.len()).map(|i| i as u8).collect(),
+            indexer,
+        };
+        let mut bytes = Vec::new();
+        tablebase.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), HEADER_LEN + tablebase.len());
+        let read = Tablebase::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(
+            (read.config(), read.rules(), read.max_outside()),
+            (&BoardConfig::SMALL, rules, 1)
+        );
+        assert_eq!(read.values, tablebase.values);
+
+        // move rules and stalling policy are read separately
+        let mut other_rules = bytes.clone();
+        other_rules[10] = RuleSet::DIAGONAL.to_bits();
+        other_rules[11..14].copy_from_slice(&StallingPolicy::OFFICIAL.to_bytes());
+        let read = Tablebase::read_from(other_rules.as_slice()).unwrap();
+        assert_eq!(
+            read.rules(),
+            RuleSet {
+                stalling: StallingPolicy::OFFICIAL,
+                ..RuleSet::DIAGONAL
+            }
+        );
+
+        let classic = &BoardConfig::CLASSIC;
+        let classic_dims = [
+            classic.width,
+            classic.height,
+            classic.camp_width,
+            classic.camp_height,
+        ];
+        for (range, field, message) in [
+            (
+                6..10,
+                &classic_dims[..],
+                "tablebase size doesn't match its header",
+            ),
+            (6..10, &[6, 6, 3, 2][..], "unknown board"),
+            (5..6, &[2][..], "tablebase size doesn't match its header"),
+            (10..11, &[1 << 4][..], "unknown rules"),
+            (11..12, &[1 << 3][..], "unknown stalling policy"),
+        ] {
+            let mut bytes = bytes.clone();
+            bytes[range].copy_from_slice(field);
+            let err = Tablebase::read_from(bytes.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{field:?}");
+            assert_eq!(err.to_string(), message);
+        }
+
+        // body shorter than the header promises
+        let err = Tablebase::read_from(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = Tablebase::read_from(&bytes[..HEADER_LEN]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
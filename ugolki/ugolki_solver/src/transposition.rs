@@ -0,0 +1,391 @@
+use crate::{BoardPosition, EvaluationResult, PieceColor, Position, Turn};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering::Relaxed};
+
+pub(crate) const fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const PIECE_KEYS: [[u64; Position::COUNT]; 2] = {
+    let mut keys = [[0; Position::COUNT]; 2];
+    let mut i = 0;
+    while i < 2 * Position::COUNT {
+        keys[i / Position::COUNT][i % Position::COUNT] = splitmix64(i as u64);
+        i += 1;
+    }
+    keys
+};
+
+const SIDE_KEY: u64 = splitmix64(2 * Position::COUNT as u64);
+
+/// Zobrist key of single piece
+#[must_use]
+#[inline]
+pub const fn piece_key(color: PieceColor, pos: Position) -> u64 {
+    PIECE_KEYS[color as usize][pos.0 as usize]
+}
+
+/// Zobrist key of side to move (mixed in when it's white's turn)
+#[must_use]
+#[inline]
+pub const fn side_key(color: PieceColor) -> u64 {
+    match color {
+        PieceColor::Black => 0,
+        PieceColor::White => SIDE_KEY,
+    }
+}
+
+/// Mixed into table keys of searches for black when their evaluations aren't symmetric
+/// (like with nonzero draw score)
+#[must_use]
+#[inline]
+pub const fn root_key(root_color: PieceColor) -> u64 {
+    match root_color {
+        PieceColor::Black => splitmix64(2 * Position::COUNT as u64 + 1),
+        PieceColor::White => 0,
+    }
+}
+
+/// Hash change after `color` performs `turn` (moving piece and passing the turn)
+#[must_use]
+#[inline]
+pub const fn turn_key(color: PieceColor, turn: Turn) -> u64 {
+    piece_key(color, turn.from) ^ piece_key(color, turn.to) ^ SIDE_KEY
+}
+
+/// Evaluation depends on turns count, so it's mixed into table keys
+#[must_use]
+#[inline]
+pub const fn turns_count_key(turns_count: usize) -> u64 {
+    splitmix64(!(turns_count as u64))
+}
+
+impl BoardPosition {
+    /// Full zobrist hash of position with `side_to_move`
+    ///
+    /// Search keeps it up to date incrementally with [`turn_key`]
+    #[must_use]
+    #[inline]
+    pub fn zobrist_hash(self, side_to_move: PieceColor) -> u64 {
+        let mut hash = side_key(side_to_move);
+        for color in [PieceColor::Black, PieceColor::White] {
+            for pos in self.0[color as usize].positions_iter() {
+                hash ^= piece_key(color, pos);
+            }
+        }
+        hash
+    }
+}
+
+/// How stored value relates to the real one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// Search failed high, real value is at least stored one
+    Lower,
+    /// Search failed low, real value is at most stored one
+    Upper,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct TableEntry {
+    pub value: EvaluationResult,
+    pub best_turn: Option<Turn>,
+    pub depth: u8,
+    pub bound: Bound,
+    generation: u8,
+}
+
+// Entry packed into single u64:
+// bits 0..32 - value payload, 32..34 - value kind, 34..41 - turn from, 41..48 - turn to
+// (`from == to` if there is no turn), 48..55 - depth, 55..57 - bound, 57..63 - generation
+const MAX_DEPTH: usize = 127;
+const GENERATION_MASK: u8 = 63;
+
+impl TableEntry {
+    #[inline]
+    fn pack(self) -> u64 {
+        let (kind, payload) = match self.value {
+            EvaluationResult::Defeat { in_steps } => (0, in_steps),
+            EvaluationResult::Victory { in_steps } => (1, in_steps),
+            EvaluationResult::InBetween { score } => (2, score),
+            EvaluationResult::Draw => (3, 0),
+        };
+        let (from, to) = self
+            .best_turn
+            .map_or((0, 0), |turn| (turn.from.0 as u64, turn.to.0 as u64));
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+
+        payload as u32 as u64
+            | kind << 32
+            | from << 34
+            | to << 41
+            | (self.depth as u64) << 48
+            | bound << 55
+            | (self.generation as u64) << 57
+    }
+
+    #[inline]
+    fn unpack(data: u64) -> Self {
+        let payload = data as u32 as i32;
+        let value = match data >> 32 & 3 {
+            0 => EvaluationResult::Defeat { in_steps: payload },
+            1 => EvaluationResult::Victory { in_steps: payload },
+            2 => EvaluationResult::InBetween { score: payload },
+            _ => EvaluationResult::Draw,
+        };
+        let from = Position((data >> 34 & 127) as u8);
+        let to = Position((data >> 41 & 127) as u8);
+        let bound = match data >> 55 & 3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+
+        Self {
+            value,
+            best_turn: (from != to).then_some(Turn { from, to }),
+            depth: (data >> 48 & 127) as u8,
+            bound,
+            generation: (data >> 57) as u8 & GENERATION_MASK,
+        }
+    }
+}
+
+/// Key is stored xored with data, so entry torn by concurrent writes is never matched
+#[derive(Debug, Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Fixed size hash table with search results, shared by all search threads without locking
+///
+/// Entries survive between searches. Slot is replaced when it's empty,
+/// left from earlier search or searched not deeper than new one.
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    generation: AtomicU8,
+}
+
+impl TranspositionTable {
+    pub const DEFAULT_SIZE_MB: usize = 32;
+
+    /// Creates table with the largest power of two entries count fitting in `size_mb`
+    #[must_use]
+    pub fn new(size_mb: usize) -> Self {
+        let max_entries = (size_mb << 20) / std::mem::size_of::<Slot>();
+        let len = if max_entries == 0 {
+            1
+        } else {
+            1 << max_entries.ilog2()
+        };
+        Self {
+            slots: (0..len).map(|_| Slot::default()).collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Marks all stored entries as old, they are still used but replaced first
+    #[inline]
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Relaxed);
+            slot.data.store(0, Relaxed);
+        }
+        self.generation.store(0, Relaxed);
+    }
+
+    #[inline]
+    fn slot(&self, key: u64) -> &Slot {
+        &self.slots[key as usize & (self.slots.len() - 1)]
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        let slot = self.slot(key);
+        let data = slot.data.load(Relaxed);
+        (data != 0 && slot.key.load(Relaxed) ^ data == key).then(|| TableEntry::unpack(data))
+    }
+
+    #[inline]
+    pub fn store(
+        &self,
+        key: u64,
+        value: EvaluationResult,
+        best_turn: Option<Turn>,
+        depth: usize,
+        bound: Bound,
+    ) {
+        let generation = self.generation.load(Relaxed) & GENERATION_MASK;
+        let slot = self.slot(key);
+        let old_data = slot.data.load(Relaxed);
+        let old_key = slot.key.load(Relaxed) ^ old_data;
+        let old = TableEntry::unpack(old_data);
+        let depth = depth.clamp(1, MAX_DEPTH) as u8;
+
+        if old_data == 0 || old.generation != generation || depth >= old.depth {
+            // keep known best turn if new search of this position didn't find one
+            let best_turn = best_turn.or(if old_key == key { old.best_turn } else { None });
+            let data = TableEntry {
+                value,
+                best_turn,
+                depth,
+                bound,
+                generation,
+            }
+            .pack();
+            slot.data.store(data, Relaxed);
+            slot.key.store(key ^ data, Relaxed);
+        }
+    }
+}
+
+impl Clone for TranspositionTable {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self
+                .slots
+                .iter()
+                .map(|slot| Slot {
+                    key: AtomicU64::new(slot.key.load(Relaxed)),
+                    data: AtomicU64::new(slot.data.load(Relaxed)),
+                })
+                .collect(),
+            generation: AtomicU8::new(self.generation.load(Relaxed)),
+        }
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE_MB)
+    }
+}
+
+impl std::fmt::Debug for TranspositionTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranspositionTable")
+            .field("len", &self.slots.len())
+            .field("generation", &self.generation.load(Relaxed))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_packing_round_trip() {
+        let values = [
+            EvaluationResult::Defeat { in_steps: 0 },
+            EvaluationResult::Victory { in_steps: 31 },
+            EvaluationResult::InBetween { score: i32::MIN },
+            EvaluationResult::InBetween { score: -1 },
+            EvaluationResult::InBetween { score: i32::MAX },
+            EvaluationResult::Draw,
+        ];
+        let turns = [
+            None,
+            Some(Turn {
+                from: Position(0),
+                to: Position(119),
+            }),
+            Some(Turn {
+                from: Position(119),
+                to: Position(22),
+            }),
+        ];
+        for (i, value) in values.into_iter().enumerate() {
+            for best_turn in turns {
+                for bound in [Bound::Exact, Bound::Lower, Bound::Upper] {
+                    let entry = TableEntry {
+                        value,
+                        best_turn,
+                        depth: [1, 64, MAX_DEPTH as u8][i % 3],
+                        bound,
+                        generation: [0, 17, GENERATION_MASK][i % 3],
+                    };
+                    let unpacked = TableEntry::unpack(entry.pack());
+                    assert_eq!(
+                        (
+                            unpacked.value,
+                            unpacked.best_turn,
+                            unpacked.depth,
+                            unpacked.bound,
+                            unpacked.generation
+                        ),
+                        (value, best_turn, entry.depth, bound, entry.generation)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn deeper_entries_are_kept() {
+        let table = TranspositionTable::new(1);
+        let key = 0x1234_5678_9abc_def0;
+        let turn = Turn {
+            from: Position(22),
+            to: Position(23),
+        };
+        assert!(table.probe(key).is_none());
+
+        let even = EvaluationResult::InBetween { score: 0 };
+        table.store(key, even, Some(turn), 6, Bound::Exact);
+        table.store(key, even, None, 2, Bound::Lower);
+        let entry = table.probe(key).unwrap();
+        assert_eq!((entry.depth, entry.bound), (6, Bound::Exact));
+
+        // entries of the earlier searches are replaced, keeping the best turn
+        table.new_search();
+        let value = EvaluationResult::InBetween { score: 5 };
+        table.store(key, value, None, 2, Bound::Lower);
+        let entry = table.probe(key).unwrap();
+        assert_eq!((entry.value, entry.best_turn), (value, Some(turn)));
+
+        // other key of the same slot
+        assert!(table.probe(key ^ (table.len() as u64) << 8).is_none());
+        table.clear();
+        assert!(table.probe(key).is_none());
+    }
+
+    #[test]
+    fn incremental_hash_matches_full_one() {
+        let mut board = BoardPosition::default();
+        let mut color = PieceColor::White;
+        let mut hash = board.zobrist_hash(color);
+        for _ in 0..20 {
+            let turn = board.generate_all_turns_for(color).last().unwrap();
+            hash ^= turn_key(color, turn);
+            board = board.perform_turn(turn, color);
+            color = color.opposite();
+            assert_eq!(hash, board.zobrist_hash(color));
+        }
+    }
+}
@@ -0,0 +1,418 @@
+//! Texel-style tuning of [`EvalWeights`] against outcomes of played games
+//!
+//! Every position of the finished games is labelled with the game result. Evaluation
+//! is mapped to the expected result with a sigmoid, and weights are adjusted by local
+//! search to minimise mean squared error of this prediction.
+//!
+//! Games are read from [`pgn`](crate::pgn) files or from the compact tuning format: one game
+//! per line, turns separated by whitespace, each turn is `from-to` with square indices
+//! (`x + width * y`) and White moves first from the initial position. In both cases the
+//! result is taken from the final position, so unfinished games are skipped, as well as
+//! the games of other boards or rules.
+
+use crate::eval::{EvalWeights, Evaluator};
+use crate::pgn::{parse_games, PgnError, PgnGame};
+use crate::{BoardConfig, BoardPosition, GameOutcome, PieceColor, Position, RuleSet, Turn};
+use std::io::{self, BufRead, Write};
+
+/// Expected result for the evaluation
+#[inline]
+fn sigmoid(scale: f64, eval: i32) -> f64 {
+    1. / (1. + (-scale * eval as f64).exp())
+}
+
+#[derive(Debug, Copy, Clone)]
+struct TuningPosition {
+    board: BoardPosition,
+    turns_count: usize,
+    /// 1 if white won the game, 0 if black did, 0.5 for draw
+    result: f64,
+}
+
+/// Positions from played games with their results
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    config: &'static BoardConfig,
+    rules: RuleSet,
+    positions: Vec<TuningPosition>,
+    /// Sigmoid steepness mapping evaluation to expected result
+    scale: f64,
+}
+
+impl Tuner {
+    /// Tuner for games on the classic board with the classic rules
+    #[must_use]
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_config(&BoardConfig::CLASSIC, RuleSet::CLASSIC)
+    }
+
+    /// Tuner for games on the `config` board played under `rules`
+    #[must_use]
+    #[inline]
+    pub fn with_config(config: &'static BoardConfig, rules: RuleSet) -> Self {
+        Self {
+            config,
+            rules,
+            positions: Vec::new(),
+            scale: 1e-4,
+        }
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn config(&self) -> &'static BoardConfig {
+        self.config
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    /// Number of collected positions
+    #[must_use]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    #[must_use]
+    #[inline]
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Adds positions of the game played from the initial position
+    ///
+    /// Returns `false` (adding nothing) if game has illegal turns or isn't finished.
+    pub fn add_game(&mut self, turns: &[Turn]) -> bool {
+        self.add_turns(
+            BoardPosition::initial(self.config),
+            PieceColor::White,
+            0,
+            turns,
+        )
+    }
+
+    /// Adds positions of the recorded game
+    ///
+    /// Returns `false` (adding nothing) if game is played on other board or under other
+    /// rules, has illegal turns or isn't finished.
+    pub fn add_pgn_game(&mut self, game: &PgnGame) -> bool {
+        let start = game.start;
+        if start.board.config() != self.config || start.rules != self.rules {
+            return false;
+        }
+        let turns = game.turns.iter().map(|turn| turn.turn).collect::<Vec<_>>();
+        self.add_turns(start.board, start.side_to_move, start.turns_count, &turns)
+    }
+
+    fn add_turns(
+        &mut self,
+        mut board: BoardPosition,
+        mut side: PieceColor,
+        start_turns_count: usize,
+        turns: &[Turn],
+    ) -> bool {
+        let mut positions = Vec::with_capacity(turns.len());
+        for (i, turn) in turns.iter().enumerate() {
+            let turns_count = start_turns_count + i;
+            if board.has_winner_with(turns_count, self.rules)
+                || !board.is_valid_turn_with(*turn, side, self.rules)
+            {
+                return false;
+            }
+            positions.push((board, turns_count));
+            board = board.perform_turn(*turn, side);
+            side = side.opposite();
+        }
+
+        let result = match board.winner_with(start_turns_count + turns.len(), self.rules) {
+            Some(GameOutcome::Win(PieceColor::White)) => 1.,
+            Some(GameOutcome::Win(PieceColor::Black)) => 0.,
+            Some(GameOutcome::Draw) => 0.5,
+            None => return false,
+        };
+        self.positions.extend(
+            positions
+                .into_iter()
+                .map(|(board, turns_count)| TuningPosition {
+                    board,
+                    turns_count,
+                    result,
+                }),
+        );
+        true
+    }
+
+    /// Adds games in the text format (see [module docs](self)), returns number of games added
+    pub fn read_games(&mut self, reader: impl BufRead) -> io::Result<usize> {
+        let mut added = 0;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let turns = parse_game(self.config, &line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: invalid turn", i + 1),
+                )
+            })?;
+            added += self.add_game(&turns) as usize;
+        }
+        Ok(added)
+    }
+
+    /// Adds games of the [`pgn`](crate::pgn) text, returns number of games added
+    pub fn read_pgn(&mut self, text: &str) -> Result<usize, PgnError> {
+        let mut added = 0;
+        for game in parse_games(text)? {
+            added += self.add_pgn_game(&game) as usize;
+        }
+        Ok(added)
+    }
+
+    /// Mean squared error of results predicted with `weights`
+    #[must_use]
+    pub fn error(&self, weights: &EvalWeights) -> f64 {
+        self.evaluations(weights)
+            .into_iter()
+            .zip(&self.positions)
+            .map(|(eval, position)| (position.result - sigmoid(self.scale, eval)).powi(2))
+            .sum::<f64>()
+            / self.positions.len().max(1) as f64
+    }
+
+    /// Chooses sigmoid steepness fitting `weights` best, should be called before [`tune`](Self::tune)
+    pub fn fit_scale(&mut self, weights: &EvalWeights) {
+        let evaluations = self.evaluations(weights);
+        let error = |scale: f64| {
+            evaluations
+                .iter()
+                .zip(&self.positions)
+                .map(|(eval, position)| (position.result - sigmoid(scale, *eval)).powi(2))
+                .sum::<f64>()
+        };
+
+        // scales from 1e-8 to 1 on logarithmic grid
+        self.scale = (0..=160)
+            .map(|i| 10f64.powf(-8. + i as f64 * 0.05))
+            .map(|scale| (scale, error(scale)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(self.scale, |(scale, _)| scale);
+    }
+
+    /// Local search from `weights`, each pass tries to move every weight up and down
+    ///
+    /// Step starts at 1/8 of the weight and is halved when a pass finds nothing better.
+    /// Stops after `max_passes` passes or when even steps of 1 don't help.
+    #[must_use]
+    pub fn tune(&self, mut weights: EvalWeights, max_passes: usize) -> EvalWeights {
+        let mut best_error = self.error(&weights);
+        let mut steps = weights.values().map(|value| (value.abs() / 8).max(1));
+
+        for _ in 0..max_passes {
+            let mut improved = false;
+            for (i, step) in steps.iter().enumerate() {
+                for direction in [1, -1] {
+                    let mut candidate = weights;
+                    *candidate.values_mut()[i] += direction * step;
+                    let error = self.error(&candidate);
+                    if error < best_error {
+                        weights = candidate;
+                        best_error = error;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+
+            if !improved {
+                if steps.iter().all(|step| *step == 1) {
+                    break;
+                }
+                steps = steps.map(|step| (step / 2).max(1));
+            }
+        }
+        weights
+    }
+
+    /// Evaluations for white of all positions, computed by all available threads
+    fn evaluations(&self, weights: &EvalWeights) -> Vec<i32> {
+        let threads = std::thread::available_parallelism().map_or(1, usize::from);
+        let chunk_size = self.positions.len().div_ceil(threads).max(1);
+        let rules = self.rules;
+        std::thread::scope(|scope| {
+            let chunks = self
+                .positions
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|position| {
+                                weights.evaluate(
+                                    position.board,
+                                    rules,
+                                    PieceColor::White,
+                                    position.turns_count,
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            chunks
+                .into_iter()
+                .flat_map(|chunk| chunk.join().expect("evaluation thread panicked"))
+                .collect()
+        })
+    }
+}
+
+impl Default for Tuner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Square index of the text format
+#[inline]
+fn square_index(config: &BoardConfig, pos: Position) -> u32 {
+    pos.get_x() as u32 + config.width as u32 * pos.get_y() as u32
+}
+
+/// Parses single game line of `config` board, `None` if any turn is malformed
+#[must_use]
+pub fn parse_game(config: &BoardConfig, line: &str) -> Option<Vec<Turn>> {
+    let squares = config.width as u32 * config.height as u32;
+    let position = |index: &str| {
+        let index = index.parse::<u32>().ok().filter(|index| *index < squares)?;
+        Some(Position::from_xy(
+            (index % config.width as u32) as u8,
+            (index / config.width as u32) as u8,
+        ))
+    };
+    line.split_whitespace()
+        .map(|turn| {
+            let (from, to) = turn.split_once('-')?;
+            Some(Turn {
+                from: position(from)?,
+                to: position(to)?,
+            })
+        })
+        .collect()
+}
+
+/// Writes game of `config` board as a single line of the text format
+pub fn write_game(mut writer: impl Write, config: &BoardConfig, turns: &[Turn]) -> io::Result<()> {
+    for (i, turn) in turns.iter().enumerate() {
+        let separator = if i == 0 { "" } else { " " };
+        write!(
+            writer,
+            "{separator}{}-{}",
+            square_index(config, turn.from),
+            square_index(config, turn.to)
+        )?;
+    }
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::random_positions;
+
+    #[test]
+    fn game_line_round_trip() {
+        for config in BoardConfig::VARIANTS {
+            let mut board = BoardPosition::initial(config);
+            let mut side = PieceColor::White;
+            let mut turns = Vec::new();
+            for _ in 0..30 {
+                let turn = board.generate_all_turns_for(side).last().unwrap();
+                turns.push(turn);
+                board = board.perform_turn(turn, side);
+                side = side.opposite();
+            }
+            let mut line = Vec::new();
+            write_game(&mut line, config, &turns).unwrap();
+            let line = String::from_utf8(line).unwrap();
+            assert_eq!(parse_game(config, &line), Some(turns), "{config}");
+        }
+    }
+
+    #[test]
+    fn malformed_games() {
+        let config = &BoardConfig::CLASSIC;
+        for line in ["18-26 x", "18", "18-64", "18--26"] {
+            assert_eq!(parse_game(config, line), None, "{line}");
+        }
+
+        let mut tuner = Tuner::new();
+        // unfinished game is skipped
+        assert_eq!(tuner.read_games("18-26\n\n".as_bytes()).unwrap(), 0);
+        let err = tuner.read_games("18-26\n18-x\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "line 2: invalid turn");
+        assert_eq!(tuner.read_pgn("1. c3-c4 *").unwrap(), 0);
+        assert!(tuner.is_empty());
+    }
+
+    /// Random positions labelled by the sign of `weights` evaluation
+    fn synthetic_tuner(weights: &EvalWeights) -> Tuner {
+        let mut tuner = Tuner::new();
+        for (i, board) in random_positions(&BoardConfig::CLASSIC, 400)
+            .into_iter()
+            .enumerate()
+        {
+            let turns_count = i % 60;
+            let eval = weights.evaluate(board, RuleSet::CLASSIC, PieceColor::White, turns_count);
+            tuner.positions.push(TuningPosition {
+                board,
+                turns_count,
+                result: match eval.cmp(&0) {
+                    std::cmp::Ordering::Less => 0.,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Greater => 1.,
+                },
+            });
+        }
+        tuner
+    }
+
+    #[test]
+    fn tuning_moves_towards_better_weights() {
+        let mut tuner = synthetic_tuner(&EvalWeights::DEFAULT);
+        tuner.fit_scale(&EvalWeights::DEFAULT);
+        let wrong = EvalWeights {
+            camp_distance: 1000,
+            ..EvalWeights::DEFAULT
+        };
+        assert!(tuner.error(&wrong) > tuner.error(&EvalWeights::DEFAULT));
+
+        let tuned = tuner.tune(wrong, 20);
+        assert!(tuner.error(&tuned) < tuner.error(&wrong));
+        let target = EvalWeights::DEFAULT.camp_distance;
+        assert!(
+            tuned.camp_distance.abs_diff(target) < wrong.camp_distance.abs_diff(target),
+            "{tuned:?}"
+        );
+
+        // already best weights are kept
+        let kept = tuner.tune(tuned, 20);
+        assert!(tuner.error(&kept) <= tuner.error(&tuned));
+
+        let mut file = Vec::new();
+        tuned.write_to(&mut file).unwrap();
+        assert_eq!(EvalWeights::read_from(file.as_slice()).unwrap(), tuned);
+    }
+}